@@ -0,0 +1,349 @@
+use crate::{
+    AuthoritativeHistory, ResourceHistory, StoreFor, TickData,
+    history::{
+        PredictedHistory, RollbackRegistry,
+        component_history::{self, ComponentHistory},
+    },
+};
+
+use std::{
+    fmt::{Debug, Display},
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::{
+        component::{ComponentId, Components},
+        entity_disabling::Disabled,
+        system::Command,
+    },
+    prelude::*,
+};
+use bevy_replicon::{
+    client::{confirm_history::ConfirmHistory, server_mutate_ticks::ServerMutateTicks},
+    shared::replicon_tick::RepliconTick,
+};
+use serde::{Deserialize, Serialize};
+
+/// A human-readable snapshot of all rollback histories, values are stored using their `Debug`
+/// representation so dumps can be loaded and compared without the types that produced them.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct RollbackDump {
+    /// The tick the latest values were stored for
+    pub store_tick: Option<u32>,
+    /// The ticks the server confirmed for all entities
+    pub confirmed: Vec<u32>,
+    /// The histories of all entities with histories
+    pub entities: Vec<EntityDump>,
+    /// The histories of all predicted resources
+    pub resources: Vec<HistoryDump>,
+}
+
+/// The histories of a single entity
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EntityDump {
+    /// The entity, as returned by [`Entity::to_bits`]
+    pub entity: u64,
+    /// The ticks the server confirmed for this entity
+    pub confirmed: Vec<u32>,
+    /// The predicted histories of the entity's components
+    pub predicted: Vec<HistoryDump>,
+    /// The authoritative histories of the entity's components
+    pub authoritative: Vec<HistoryDump>,
+}
+
+/// The history of a single component or resource
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct HistoryDump {
+    /// The name of the component or resource
+    pub name: String,
+    /// The stored values and the ticks they were stored for
+    pub ticks: Vec<(u32, TickData<String>)>,
+}
+
+/// An error while saving or loading a [`RollbackDump`]
+#[derive(Debug)]
+pub enum DumpError {
+    /// Failed to read or write the file
+    Io(std::io::Error),
+    /// Failed to serialize the dump
+    Serialize(ron::Error),
+    /// Failed to deserialize the dump
+    Deserialize(ron::error::SpannedError),
+}
+
+impl Display for DumpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpError::Io(e) => write!(f, "io error: {e}"),
+            DumpError::Serialize(e) => write!(f, "failed to serialize dump: {e}"),
+            DumpError::Deserialize(e) => write!(f, "failed to deserialize dump: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+impl RollbackDump {
+    /// Capture the rollback histories currently stored in the world
+    pub fn from_world(world: &mut World) -> Self {
+        let mut query = world.query_filtered::<(
+            Entity,
+            Option<&PredictedHistory>,
+            Option<&AuthoritativeHistory>,
+            Option<&ConfirmHistory>,
+        ), (
+            Or<(With<PredictedHistory>, With<AuthoritativeHistory>)>,
+            Or<(With<Disabled>, Without<Disabled>)>,
+        )>();
+        let world: &World = world;
+
+        let mut entities = query
+            .iter(world)
+            .map(|(entity, predicted, authoritative, confirm)| {
+                let predicted = predicted
+                    .map(|h| dump_histories(world, h.iter()))
+                    .unwrap_or_default();
+                let authoritative = authoritative
+                    .map(|h| dump_histories(world, h.iter()))
+                    .unwrap_or_default();
+                let confirmed = confirm
+                    .map(|confirm| {
+                        tick_range([&predicted, &authoritative])
+                            .filter(|&tick| confirm.contains(RepliconTick::new(tick)))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                EntityDump {
+                    entity: entity.to_bits(),
+                    confirmed,
+                    predicted,
+                    authoritative,
+                }
+            })
+            .collect::<Vec<_>>();
+        entities.sort_by_key(|e| e.entity);
+
+        let confirmed = world
+            .get_resource::<ServerMutateTicks>()
+            .map(|global| {
+                tick_range(
+                    entities
+                        .iter()
+                        .flat_map(|e| [&e.predicted, &e.authoritative]),
+                )
+                .filter(|&tick| {
+                    let tick = RepliconTick::new(tick);
+                    global.contains_any(tick, tick)
+                })
+                .collect()
+            })
+            .unwrap_or_default();
+
+        let resources = world
+            .get_resource::<ResourceDumpRegistry>()
+            .map(|registry| registry.0.iter().filter_map(|dump| dump(world)).collect())
+            .unwrap_or_default();
+
+        Self {
+            store_tick: world.get_resource::<StoreFor>().map(|tick| tick.get()),
+            confirmed,
+            entities,
+            resources,
+        }
+    }
+
+    /// Serialize the dump to a RON string
+    pub fn to_ron(&self) -> Result<String, DumpError> {
+        ron::ser::to_string_pretty(self, default()).map_err(DumpError::Serialize)
+    }
+
+    /// Deserialize a dump from a RON string
+    pub fn from_ron(ron: &str) -> Result<Self, DumpError> {
+        ron::from_str(ron).map_err(DumpError::Deserialize)
+    }
+
+    /// Write the dump to a RON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DumpError> {
+        std::fs::write(path, self.to_ron()?).map_err(DumpError::Io)
+    }
+
+    /// Load a dump from a RON file, for example to compare it to another dump
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DumpError> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(DumpError::Io)?)
+    }
+}
+
+/// A command that writes all rollback histories to a RON file, see [`RollbackDump`]
+pub struct DumpRollbackHistory(pub PathBuf);
+
+impl Command for DumpRollbackHistory {
+    fn apply(self, world: &mut World) {
+        if let Err(e) = RollbackDump::from_world(world).save(&self.0) {
+            error!(
+                "Failed to dump rollback history to {}: {e}",
+                self.0.display()
+            );
+        }
+    }
+}
+
+/// Functions to dump the histories of registered predicted resources
+#[derive(Resource, Default)]
+pub(crate) struct ResourceDumpRegistry(Vec<fn(&World) -> Option<HistoryDump>>);
+
+pub(crate) fn register_resource<T: Resource + Debug>(world: &mut World) {
+    world
+        .get_resource_or_init::<ResourceDumpRegistry>()
+        .0
+        .push(dump_resource::<T>);
+}
+
+fn dump_resource<T: Resource + Debug>(world: &World) -> Option<HistoryDump> {
+    let history = world.get_resource::<ResourceHistory<T>>()?;
+    Some(HistoryDump {
+        name: std::any::type_name::<T>().into(),
+        ticks: history
+            .iter()
+            .map(|(tick, value)| {
+                let value = match value {
+                    TickData::Value(value) => TickData::Value(format!("{value:?}")),
+                    TickData::Removed => TickData::Removed,
+                    TickData::Missing => TickData::Missing,
+                };
+                (tick.get(), value)
+            })
+            .collect(),
+    })
+}
+
+fn dump_histories<'a>(
+    world: &World,
+    histories: impl Iterator<Item = (&'a ComponentId, &'a ComponentHistory)>,
+) -> Vec<HistoryDump> {
+    let registry = world.resource::<RollbackRegistry>();
+    let mut dumps = histories
+        .filter_map(|(id, history)| {
+            let component = &registry.components[*registry.ids.get(id)?];
+            component.check_history(history);
+
+            // Unchanged values are not stored, so we only dump the ticks with changes
+            let ticks = (history.first_tick()..=history.last_tick())
+                .filter_map(|tick| match history.get(tick) {
+                    // SAFETY: The history was created for this component
+                    component_history::TickData::Value(ptr) => Some((
+                        tick,
+                        TickData::Value(unsafe { component.debug_stored(ptr) }),
+                    )),
+                    component_history::TickData::Removed => Some((tick, TickData::Removed)),
+                    component_history::TickData::Missing => None,
+                })
+                .collect();
+
+            Some(HistoryDump {
+                name: component_name(world.components(), *id),
+                ticks,
+            })
+        })
+        .collect::<Vec<_>>();
+    dumps.sort_by(|a, b| a.name.cmp(&b.name));
+    dumps
+}
+
+fn component_name(components: &Components, id: ComponentId) -> String {
+    components
+        .get_info(id)
+        .map(|info| info.name().to_string())
+        .unwrap_or_else(|| format!("{id:?}"))
+}
+
+/// The range of ticks covered by the histories
+fn tick_range<'a>(
+    histories: impl IntoIterator<Item = &'a Vec<HistoryDump>>,
+) -> impl Iterator<Item = u32> {
+    let (min, max) = histories
+        .into_iter()
+        .flatten()
+        .flat_map(|h| &h.ticks)
+        .fold((u32::MAX, 0), |(min, max), &(tick, _)| {
+            (min.min(tick), max.max(tick))
+        });
+    min..=max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::test_utils::*;
+
+    #[derive(Resource, Clone, Debug)]
+    struct Score(#[expect(dead_code)] u8);
+
+    #[test]
+    fn dump_and_load() {
+        let mut world = World::new();
+        world.init_resource::<ServerMutateTicks>();
+        world
+            .resource_mut::<ServerMutateTicks>()
+            .confirm(r_tick(2), 1);
+
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(&mut world);
+        world.insert_resource(registry);
+        let comp_a = world.register_component::<A>();
+
+        let entity = world
+            .spawn((
+                pred_history(
+                    1,
+                    comp_a,
+                    [a(1), a(2), component_history::TickData::Removed],
+                ),
+                auth_history(1, comp_a, [a(5)]),
+                confirm_history([1]),
+            ))
+            .id();
+
+        world.insert_resource(ResourceHistory::from_list(
+            2,
+            [TickData::Value(Score(3)), TickData::Removed],
+        ));
+        register_resource::<Score>(&mut world);
+
+        let dump = RollbackDump::from_world(&mut world);
+        let a_name = std::any::type_name::<A>().to_string();
+        assert_eq!(
+            RollbackDump {
+                store_tick: None,
+                confirmed: vec![2],
+                entities: vec![EntityDump {
+                    entity: entity.to_bits(),
+                    confirmed: vec![1],
+                    predicted: vec![HistoryDump {
+                        name: a_name.clone(),
+                        ticks: vec![
+                            (1, TickData::Value("A(1)".into())),
+                            (2, TickData::Value("A(2)".into())),
+                            (3, TickData::Removed),
+                        ],
+                    }],
+                    authoritative: vec![HistoryDump {
+                        name: a_name,
+                        ticks: vec![(1, TickData::Value("A(5)".into()))],
+                    }],
+                }],
+                resources: vec![HistoryDump {
+                    name: std::any::type_name::<Score>().into(),
+                    ticks: vec![
+                        (2, TickData::Value("Score(3)".into())),
+                        (3, TickData::Removed)
+                    ],
+                }],
+            },
+            dump
+        );
+
+        let loaded = RollbackDump::from_ron(&dump.to_ron().unwrap()).unwrap();
+        assert_eq!(dump, loaded);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{
+    Resimulated, RollbackFrames, RollbackSchedule, StoreFor, StoreScheduleLabel, TickSource,
+};
+
+use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+
+use bevy::{
+    ecs::system::SystemParam,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// A trait for effect reasons, which identify a one-shot effect along with its tick
+pub trait EffectReason: Eq + Hash + Clone + Debug + Send + Sync + 'static {}
+
+impl<T> EffectReason for T where T: Eq + Hash + Clone + Debug + Send + Sync + 'static {}
+
+/// A plugin adding rollback-safe one-shot effects for a specific [`EffectReason`].
+///
+/// Effects are keyed by the tick they are triggered on and their reason. Triggering an effect
+/// again while resimulating doesn't trigger it twice, and effects that aren't triggered again
+/// when their tick is resimulated are reported through [`EffectCancelled`], so sounds or particles
+/// can be stopped. Effects triggered for an entity with [`Effects::trigger_for`] are only expected
+/// again when the entity is [`Resimulated`].
+pub struct EffectPlugin<Reason: EffectReason, Tick: TickSource>(PhantomData<(Reason, Tick)>);
+
+impl<Reason: EffectReason, Tick: TickSource> Default for EffectPlugin<Reason, Tick> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Reason: EffectReason, Tick: TickSource> Plugin for EffectPlugin<Reason, Tick> {
+    fn build(&self, app: &mut App) {
+        let store_schedule = **app.world().resource::<StoreScheduleLabel>();
+        app.init_resource::<TriggeredEffects<Reason>>()
+            .add_event::<EffectTriggered<Reason>>()
+            .add_event::<EffectCancelled<Reason>>()
+            .add_systems(store_schedule, clean_effects::<Reason>)
+            .add_systems(
+                RollbackSchedule::PreResimulation,
+                mark_resimulated::<Reason, Tick>,
+            )
+            .add_systems(RollbackSchedule::PostResimulation, cancel_effects::<Reason>)
+            .add_systems(RollbackSchedule::Reset, clear_effects::<Reason>);
+    }
+}
+
+/// An event sent when an effect is triggered for the first time
+#[derive(Event, Clone, Debug)]
+pub struct EffectTriggered<Reason: EffectReason> {
+    /// The tick the effect was triggered on
+    pub tick: RepliconTick,
+    /// The reason of the effect
+    pub reason: Reason,
+}
+
+/// An event sent when an effect was not triggered again when its tick was resimulated,
+/// meaning it was mispredicted
+#[derive(Event, Clone, Debug)]
+pub struct EffectCancelled<Reason: EffectReason> {
+    /// The tick the effect was triggered on
+    pub tick: RepliconTick,
+    /// The reason of the effect
+    pub reason: Reason,
+}
+
+#[derive(Resource)]
+struct TriggeredEffects<Reason: EffectReason> {
+    // The triggered effects, along with the entity they were triggered for
+    effects: HashMap<(RepliconTick, Reason), Option<Entity>>,
+    // Effects that still need to be triggered again on the tick being resimulated
+    pending: HashSet<(RepliconTick, Reason)>,
+}
+
+impl<Reason: EffectReason> Default for TriggeredEffects<Reason> {
+    fn default() -> Self {
+        Self {
+            effects: HashMap::default(),
+            pending: HashSet::default(),
+        }
+    }
+}
+
+/// A system param used to trigger one-shot effects from the simulation
+#[derive(SystemParam)]
+pub struct Effects<'w, Reason: EffectReason, Tick: TickSource> {
+    effects: ResMut<'w, TriggeredEffects<Reason>>,
+    triggered: EventWriter<'w, EffectTriggered<Reason>>,
+    tick: Res<'w, Tick>,
+}
+
+impl<Reason: EffectReason, Tick: TickSource> Effects<'_, Reason, Tick> {
+    /// Trigger an effect on the current tick, returns `true` if the effect should be played,
+    /// or `false` if it was already triggered before a rollback
+    pub fn trigger(&mut self, reason: Reason) -> bool {
+        self.trigger_internal(reason, None)
+    }
+
+    /// Trigger an effect caused by an entity on the current tick, like [`trigger`](Self::trigger).
+    /// During partial rollbacks the effect is only cancelled if the entity was resimulated.
+    pub fn trigger_for(&mut self, entity: Entity, reason: Reason) -> bool {
+        self.trigger_internal(reason, Some(entity))
+    }
+
+    fn trigger_internal(&mut self, reason: Reason, entity: Option<Entity>) -> bool {
+        let key = ((*self.tick).into(), reason);
+        if self.effects.effects.contains_key(&key) {
+            self.effects.pending.remove(&key);
+            return false;
+        }
+
+        let (tick, reason) = key.clone();
+        self.effects.effects.insert(key, entity);
+        self.triggered.write(EffectTriggered { tick, reason });
+        true
+    }
+}
+
+fn mark_resimulated<Reason: EffectReason, Tick: TickSource>(
+    mut effects: ResMut<TriggeredEffects<Reason>>,
+    tick: Res<Tick>,
+    resimulated: Resimulated,
+) {
+    let tick: RepliconTick = (*tick).into();
+    let TriggeredEffects { effects, pending } = &mut *effects;
+    pending.extend(
+        effects
+            .iter()
+            .filter(|&((effect_tick, _), entity)| {
+                *effect_tick == tick && entity.is_none_or(|entity| resimulated.contains(entity))
+            })
+            .map(|(key, _)| key.clone()),
+    );
+}
+
+fn cancel_effects<Reason: EffectReason>(
+    mut effects: ResMut<TriggeredEffects<Reason>>,
+    mut cancelled: EventWriter<EffectCancelled<Reason>>,
+) {
+    let TriggeredEffects { effects, pending } = &mut *effects;
+    for (tick, reason) in pending.drain() {
+        effects.remove(&(tick, reason.clone()));
+        cancelled.write(EffectCancelled { tick, reason });
+    }
+}
+
+fn clean_effects<Reason: EffectReason>(
+    mut effects: ResMut<TriggeredEffects<Reason>>,
+    tick: Res<StoreFor>,
+    frames: Res<RollbackFrames>,
+) {
+    let max_ticks = frames.history_size() as u32;
+    effects
+        .effects
+        .retain(|(effect_tick, _), _| *effect_tick + max_ticks >= **tick);
+}
+
+fn clear_effects<Reason: EffectReason>(mut effects: ResMut<TriggeredEffects<Reason>>) {
+    *effects = default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BudgetFallback, EntityReplicated, PartialRollbackPlugin, Predicted, ResimulationBudget,
+        RollbackGroup, RollbackTarget,
+        tests::{Tick, init_app},
+    };
+
+    use bevy::ecs::schedule::ScheduleLabel;
+
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    struct Hit;
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct HitAt(Option<Tick>);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    fn triggered(app: &App) -> Vec<u32> {
+        app.world()
+            .resource::<Events<EffectTriggered<Hit>>>()
+            .iter_current_update_events()
+            .map(|e| e.tick.get())
+            .collect()
+    }
+
+    fn cancelled(app: &App) -> Vec<u32> {
+        app.world()
+            .resource::<Events<EffectCancelled<Hit>>>()
+            .iter_current_update_events()
+            .map(|e| e.tick.get())
+            .collect()
+    }
+
+    #[test]
+    fn deduplicates_and_cancels_effects() {
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_plugins(EffectPlugin::<Hit, Tick>::default())
+            .init_resource::<HitAt>()
+            .insert_resource(Tick(14))
+            .add_systems(
+                FixedUpdate,
+                |mut effects: Effects<Hit, Tick>, hit_at: Res<HitAt>, tick: Res<Tick>| {
+                    if **hit_at == Some(*tick) {
+                        effects.trigger(Hit);
+                    }
+                },
+            );
+
+        **app.world_mut().resource_mut::<HitAt>() = Some(Tick(14));
+        app.update();
+        assert_eq!(triggered(&app), [14]);
+
+        // Triggering the effect again while resimulating doesn't play it twice
+        app.insert_resource(Tick(15));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert!(triggered(&app).is_empty());
+        assert!(cancelled(&app).is_empty());
+
+        // A mispredicted effect gets cancelled and triggered on the corrected tick
+        **app.world_mut().resource_mut::<HitAt>() = Some(Tick(15));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(triggered(&app), [15]);
+        assert_eq!(cancelled(&app), [14]);
+    }
+
+    #[test]
+    fn keeps_effects_of_skipped_entities() {
+        #[derive(Component)]
+        struct Hitter;
+
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_plugins((
+            EffectPlugin::<Hit, Tick>::default(),
+            PartialRollbackPlugin::<Tick>::default(),
+        ))
+        .init_resource::<HitAt>()
+        .add_systems(
+            FixedUpdate,
+            |mut effects: Effects<Hit, Tick>,
+             q: Query<Entity, With<Hitter>>,
+             resimulated: Resimulated,
+             hit_at: Res<HitAt>,
+             tick: Res<Tick>| {
+                for entity in q.iter() {
+                    if resimulated.contains(entity) && **hit_at == Some(*tick) {
+                        effects.trigger_for(entity, Hit);
+                    }
+                }
+            },
+        );
+
+        let world = app.world_mut();
+        let corrected = world.spawn((Predicted, RollbackGroup(1))).id();
+        world.spawn((Predicted, Hitter, RollbackGroup(2)));
+        **world.resource_mut::<HitAt>() = Some(Tick(15));
+        app.update();
+        assert_eq!(triggered(&app), [15]);
+
+        // The entity that triggered the effect isn't resimulated, so the effect stays
+        app.world_mut().send_event(EntityReplicated {
+            entity: corrected,
+            tick: RepliconTick::new(14),
+        });
+        app.update();
+        assert!(triggered(&app).is_empty());
+        assert!(cancelled(&app).is_empty());
+
+        // Once it is resimulated without triggering the effect, it's cancelled
+        **app.world_mut().resource_mut::<HitAt>() = None;
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(cancelled(&app), [15]);
+    }
+
+    #[test]
+    fn cancels_effects_while_catching_up() {
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_plugins(EffectPlugin::<Hit, Tick>::default())
+            .init_resource::<HitAt>()
+            .insert_resource(ResimulationBudget {
+                max_ticks: Some(1),
+                fallback: BudgetFallback::CatchUp,
+                ..default()
+            })
+            .add_systems(
+                FixedUpdate,
+                |mut effects: Effects<Hit, Tick>, hit_at: Res<HitAt>, tick: Res<Tick>| {
+                    if **hit_at == Some(*tick) {
+                        effects.trigger(Hit);
+                    }
+                },
+            );
+
+        **app.world_mut().resource_mut::<HitAt>() = Some(Tick(15));
+        app.update();
+        assert_eq!(triggered(&app), [15]);
+
+        // Tick 15 is postponed by the budget, so its effect isn't cancelled yet
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(*app.world().resource::<Tick>(), Tick(14));
+        assert!(triggered(&app).is_empty());
+        assert!(cancelled(&app).is_empty());
+
+        // Catching up on tick 15 without triggering the effect cancels it
+        **app.world_mut().resource_mut::<HitAt>() = None;
+        app.update();
+        assert!(triggered(&app).is_empty());
+        assert_eq!(cancelled(&app), [15]);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Predicted, PredictionCommandsExt, RollbackApp, RollbackStoreSet, StoreScheduleLabel};
+
+use bevy::{ecs::entity_disabling::Disabled, prelude::*};
+
+/// A plugin rolling back hierarchies, add it after the [`RollbackPlugin`](crate::RollbackPlugin).
+///
+/// [`ChildOf`] is registered as a predicted relationship, so children are attached and detached
+/// again when loading history, and [`Children`] follows. Descendants of entities marked with
+/// [`PredictHierarchy`] are predicted along with them.
+pub struct PredictedHierarchyPlugin;
+
+impl Plugin for PredictedHierarchyPlugin {
+    fn build(&self, app: &mut App) {
+        let store_schedule = **app.world().resource::<StoreScheduleLabel>();
+        app.register_predicted_relationship::<ChildOf>()
+            .add_systems(store_schedule, propagate_predicted.before(RollbackStoreSet));
+    }
+}
+
+/// A marker for [`Predicted`] entities whose descendants should be predicted as well,
+/// for example a car with child colliders and wheels.
+/// Descendants are promoted with [`promote_predicted`](crate::promote_predicted) before their
+/// values are stored, they stay predicted when they are detached.
+#[derive(Component, Clone, Copy, Default, Debug)]
+#[require(Predicted)]
+pub struct PredictHierarchy;
+
+fn propagate_predicted(
+    mut commands: Commands,
+    roots: Query<
+        Entity,
+        (
+            With<PredictHierarchy>,
+            Or<(With<Disabled>, Without<Disabled>)>,
+        ),
+    >,
+    children: Query<&Children, Or<(With<Disabled>, Without<Disabled>)>>,
+    predicted: Query<(), (With<Predicted>, Or<(With<Disabled>, Without<Disabled>)>)>,
+) {
+    for root in roots.iter() {
+        for descendant in children.iter_descendants(root) {
+            if !predicted.contains(descendant) {
+                commands.entity(descendant).promote_predicted();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        RollbackTarget,
+        tests::{Tick, init_app},
+    };
+
+    use bevy::ecs::schedule::ScheduleLabel;
+    use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    #[test]
+    fn rolls_back_hierarchy() {
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_plugins(PredictedHierarchyPlugin)
+            .insert_resource(Tick(14))
+            .add_systems(FixedUpdate, |world: &mut World| world.run_schedule(Store));
+
+        let car = app.world_mut().spawn(PredictHierarchy).id();
+        let wheel = app.world_mut().spawn(ChildOf(car)).id();
+        let hub = app.world_mut().spawn(ChildOf(wheel)).id();
+        app.update();
+        assert!(app.world().entity(wheel).contains::<Predicted>());
+        assert!(app.world().entity(hub).contains::<Predicted>());
+
+        // The wheel comes off on the next tick
+        app.insert_resource(Tick(15));
+        app.world_mut().entity_mut(wheel).remove::<ChildOf>();
+        app.update();
+        assert!(app.world().entity(car).get::<Children>().is_none());
+
+        // Rolling back to before it came off attaches it again
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(15));
+        app.update();
+        assert_eq!(
+            Some(&ChildOf(car)),
+            app.world().entity(wheel).get::<ChildOf>()
+        );
+        assert_eq!(
+            [wheel],
+            **app.world().entity(car).get::<Children>().unwrap()
+        );
+        assert_eq!(
+            Some(&ChildOf(wheel)),
+            app.world().entity(hub).get::<ChildOf>()
+        );
+    }
+}
@@ -1,12 +1,17 @@
-use super::component_history::ComponentHistory;
-use crate::{Predicted, RollbackFrames};
+use super::{
+    RollbackRegistry,
+    component::{HistoryComponent, serialize_value},
+    component_history::ComponentHistory,
+};
+use crate::{Predicted, RollbackFrames, recording};
 
 use std::{fmt::Debug, mem::ManuallyDrop, num::NonZero};
 
 use bevy::{
-    ecs::component::{ComponentId, Mutable},
+    ecs::component::ComponentId,
     platform::collections::HashMap,
     prelude::*,
+    ptr::{Ptr, PtrMut},
 };
 use bevy_replicon::{
     bytes::Bytes,
@@ -14,6 +19,7 @@ use bevy_replicon::{
         replication::{
             deferred_entity::DeferredEntity,
             registry::{
+                command_fns::MutWrite,
                 ctx::{RemoveCtx, WriteCtx},
                 rule_fns::RuleFns,
             },
@@ -21,6 +27,7 @@ use bevy_replicon::{
         replicon_tick::RepliconTick,
     },
 };
+use serde::{Serialize, de::DeserializeOwned};
 
 pub struct AuthoriativeCleanupPlugin;
 
@@ -39,9 +46,38 @@ pub struct AuthoritativeHistory {
     components: HashMap<ComponentId, ComponentHistory>,
 }
 
-pub(crate) fn write_authoritative_history<
-    T: Component<Mutability = Mutable> + Clone + PartialEq + Debug,
->(
+/// The last value received from the server for an authoritative component of an entity that
+/// isn't predicted, used to seed the entity's history when it gets
+/// [promoted](super::promote_predicted)
+#[derive(Component)]
+#[repr(transparent)]
+pub(crate) struct ConfirmedValue<T>(pub T);
+
+/// Write a received value to an entity that isn't predicted, keeping a copy as its confirmed value
+pub(crate) fn write_confirmed<T: Component<Mutability: MutWrite<T>> + Clone>(
+    ctx: &mut WriteCtx,
+    rule_fns: &RuleFns<T>,
+    entity: &mut DeferredEntity,
+    cursor: &mut Bytes,
+) -> Result<()> {
+    let value = rule_fns.deserialize(ctx, cursor)?;
+    match entity.get_mut::<ConfirmedValue<T>>() {
+        Some(mut confirmed) => confirmed.0 = value.clone(),
+        None => {
+            entity.insert(ConfirmedValue(value.clone()));
+        }
+    }
+    entity.insert(value);
+
+    Ok(())
+}
+
+/// Remove a component from an entity that isn't predicted, along with its confirmed value
+pub(crate) fn remove_confirmed<T: Component>(_: &mut RemoveCtx, entity: &mut DeferredEntity) {
+    entity.remove::<ConfirmedValue<T>>().remove::<T>();
+}
+
+pub(crate) fn write_authoritative_history<T: Component + Clone + PartialEq + Debug>(
     ctx: &mut WriteCtx,
     rule_fns: &RuleFns<T>,
     entity: &mut DeferredEntity,
@@ -54,17 +90,197 @@ pub(crate) fn write_authoritative_history<
         .copied()
         .unwrap_or_default();
 
+    record_authoritative(
+        ctx.component_id,
+        entity,
+        ctx.message_tick,
+        Some(Ptr::from(&value)),
+    );
     write_history_internal(ctx.component_id, entity, ctx.message_tick, value, frames);
 
     Ok(())
 }
 
+pub(crate) fn write_serialized_authoritative_history<
+    T: Component + Serialize + DeserializeOwned + Debug,
+>(
+    ctx: &mut WriteCtx,
+    rule_fns: &RuleFns<T>,
+    entity: &mut DeferredEntity,
+    cursor: &mut Bytes,
+) -> Result<()> {
+    let value = rule_fns.deserialize(ctx, cursor)?;
+    let frames = entity
+        .world()
+        .get_resource::<RollbackFrames>()
+        .copied()
+        .unwrap_or_default();
+
+    record_authoritative(
+        ctx.component_id,
+        entity,
+        ctx.message_tick,
+        Some(Ptr::from(&value)),
+    );
+    write_serialized_history_internal(ctx.component_id, entity, ctx.message_tick, &value, frames);
+
+    Ok(())
+}
+
+/// Write a received value of a component registered through reflection to the entity's history
+pub(crate) fn write_reflected_authoritative_history<T: Component>(
+    ctx: &mut WriteCtx,
+    rule_fns: &RuleFns<T>,
+    entity: &mut DeferredEntity,
+    cursor: &mut Bytes,
+) -> Result<()> {
+    let value = rule_fns.deserialize(ctx, cursor)?;
+    let frames = entity
+        .world()
+        .get_resource::<RollbackFrames>()
+        .copied()
+        .unwrap_or_default();
+
+    record_authoritative(
+        ctx.component_id,
+        entity,
+        ctx.message_tick,
+        Some(Ptr::from(&value)),
+    );
+    write_reflected_history_internal(ctx.component_id, entity, ctx.message_tick, &value, frames);
+
+    Ok(())
+}
+
 fn write_history_internal<T: Component + Clone + PartialEq + Debug>(
     component_id: ComponentId,
     entity: &mut DeferredEntity,
     received_tick: RepliconTick,
     value: T,
     frames: RollbackFrames,
+) {
+    write_stored_internal(
+        component_id,
+        entity,
+        received_tick,
+        value,
+        frames,
+        &HistoryComponent::new::<T>(),
+    );
+}
+
+fn write_serialized_history_internal<T: Serialize + DeserializeOwned + Debug + 'static>(
+    component_id: ComponentId,
+    entity: &mut DeferredEntity,
+    received_tick: RepliconTick,
+    value: &T,
+    frames: RollbackFrames,
+) {
+    let bytes = match serialize_value(value) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("failed to serialize {}: {e}", std::any::type_name::<T>());
+            return;
+        }
+    };
+    write_stored_internal(
+        component_id,
+        entity,
+        received_tick,
+        bytes,
+        frames,
+        &HistoryComponent::serialized::<T>(),
+    );
+}
+
+/// Write an already converted history value to the entity's history
+/// `value` MUST have the history type of `component`
+fn write_reflected_history_internal<T: Component>(
+    component_id: ComponentId,
+    entity: &mut DeferredEntity,
+    received_tick: RepliconTick,
+    value: &T,
+    frames: RollbackFrames,
+) {
+    let registry = entity.world().resource::<RollbackRegistry>();
+    let &index = registry.ids.get(&component_id).unwrap();
+    let component = registry.components[index].clone();
+
+    // SAFETY: `value` has the type the component was registered with
+    unsafe {
+        write_erased_authoritative_history(
+            component_id,
+            entity,
+            received_tick,
+            Ptr::from(value),
+            frames,
+            &component,
+        );
+    }
+}
+
+fn write_stored_internal<S>(
+    component_id: ComponentId,
+    entity: &mut DeferredEntity,
+    received_tick: RepliconTick,
+    value: S,
+    frames: RollbackFrames,
+    component: &HistoryComponent,
+) {
+    // SAFETY: `value` has the history type of `component`
+    unsafe {
+        write_with(
+            component_id,
+            entity,
+            received_tick,
+            frames,
+            component,
+            |dst| {
+                let value = ManuallyDrop::new(value);
+                std::ptr::copy_nonoverlapping(
+                    (&value as *const ManuallyDrop<S>).cast(),
+                    dst.as_ptr(),
+                    size_of::<S>(),
+                );
+            },
+        );
+    }
+}
+
+/// Write a value of the component to the entity's history, using the component's store function
+/// SAFETY: `value` MUST point to a value of `component`'s type
+pub(crate) unsafe fn write_erased_authoritative_history(
+    component_id: ComponentId,
+    entity: &mut DeferredEntity,
+    received_tick: RepliconTick,
+    value: Ptr,
+    frames: RollbackFrames,
+    component: &HistoryComponent,
+) {
+    // SAFETY: `store` converts the value to the history type of `component`
+    unsafe {
+        write_with(
+            component_id,
+            entity,
+            received_tick,
+            frames,
+            component,
+            |dst| {
+                component.store(value, dst);
+            },
+        );
+    }
+}
+
+/// Write to the entity's history for a component
+/// SAFETY: `write_fn` MUST write a value with the history type of `component`
+unsafe fn write_with(
+    component_id: ComponentId,
+    entity: &mut DeferredEntity,
+    received_tick: RepliconTick,
+    frames: RollbackFrames,
+    component: &HistoryComponent,
+    write_fn: impl FnOnce(PtrMut),
 ) {
     let Some(mut history) = entity.get_mut::<AuthoritativeHistory>() else {
         if !entity.contains::<Predicted>() {
@@ -82,32 +298,40 @@ fn write_history_internal<T: Component + Clone + PartialEq + Debug>(
     };
 
     let comp_hist = history.entry(component_id).or_insert_with(|| {
-        ComponentHistory::from_type::<T>(NonZero::new(frames.history_size() as u8).unwrap())
+        ComponentHistory::from_component(
+            component,
+            NonZero::new(frames.history_size() as u8).unwrap(),
+        )
     });
+    component.check_history(comp_hist);
 
     // TODO: Figure out deduplication of values
     // SAFETY: We are writing to a history matching our ComponentId
     unsafe {
-        comp_hist.write(received_tick.get(), |dst| {
-            let value = ManuallyDrop::new(value);
-            std::ptr::copy_nonoverlapping(
-                (&value as *const ManuallyDrop<T>).cast(),
-                dst.as_ptr(),
-                size_of::<T>(),
-            );
-        });
+        comp_hist.write(received_tick.get(), write_fn);
     }
 }
 
+/// Record a received authoritative value, if the session is being recorded
+fn record_authoritative(
+    component_id: ComponentId,
+    entity: &DeferredEntity,
+    tick: RepliconTick,
+    value: Option<Ptr>,
+) {
+    recording::record_authoritative(entity.world(), entity.id(), component_id, tick, value);
+}
+
 // TODO: Tests
-pub fn remove_authoritative_history<T: Component + Debug>(
+pub fn remove_authoritative_history<T: Component>(
     ctx: &mut RemoveCtx,
     entity: &mut DeferredEntity,
 ) {
+    record_authoritative(ctx.component_id, entity, ctx.message_tick, None);
     remove_history_internal(ctx.component_id, ctx.message_tick, entity);
 }
 
-fn remove_history_internal(
+pub(crate) fn remove_history_internal(
     component_id: ComponentId,
     tick: RepliconTick,
     entity: &mut DeferredEntity,
@@ -133,15 +357,18 @@ fn remove_history_internal(
 #[cfg(test)]
 mod tests {
     use super::{
-        super::{component_history::TickData, test_utils::*},
-        AuthoritativeHistory, write_history_internal,
+        super::{component::deserialize_value, component_history::TickData, test_utils::*},
+        AuthoritativeHistory, write_history_internal, write_reflected_history_internal,
+        write_serialized_history_internal,
     };
     use crate::RollbackFrames;
     use crate::history::RollbackRegistry;
     use TickData::*;
     use bevy_replicon::shared::replication::deferred_entity::{DeferredChanges, DeferredEntity};
 
-    use bevy::prelude::*;
+    use std::any::TypeId;
+
+    use bevy::{prelude::*, reflect::TypeRegistry};
 
     trait DeferredEntityScope {
         fn entity_scope(&mut self, entity: Entity, f: impl Fn(&mut DeferredEntity) -> ());
@@ -307,6 +534,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn write_serialized() {
+        let mut world = World::new();
+        world.init_resource::<RollbackFrames>();
+        let frames = world.resource::<RollbackFrames>().clone();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register_serialized::<S>(&mut world);
+        world.insert_resource(registry);
+        let comp_s = world.register_component::<S>();
+
+        let e1 = world.spawn(AuthoritativeHistory::default()).id();
+
+        // Write S([1, 2]) and S([3]) to e1 for tick 0 and 2 respectively
+        world.entity_scope(e1, |e| {
+            write_serialized_history_internal(comp_s, e, r_tick(0), &S(vec![1, 2]), frames);
+            write_serialized_history_internal(comp_s, e, r_tick(2), &S(vec![3]), frames);
+        });
+
+        use Missing as M;
+
+        let e = world.entity(e1);
+        let hist = e.get::<AuthoritativeHistory>().unwrap();
+        assert!(hist.contains_key(&comp_s));
+        for (i, v) in [Value(S(vec![1, 2])), M, Value(S(vec![3])), M].iter_enumerate() {
+            let stored = hist.get(&comp_s).unwrap().get(i as u32);
+            assert_eq!(
+                v,
+                stored
+                    .deref::<Vec<u8>>()
+                    .map(|bytes| deserialize_value::<S>(bytes).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn write_reflected() {
+        let mut world = World::new();
+        world.init_resource::<RollbackFrames>();
+        let frames = world.resource::<RollbackFrames>().clone();
+
+        let mut types = TypeRegistry::new();
+        types.register::<R>();
+        let mut registry = RollbackRegistry::default();
+        registry.register_reflect(&mut world, types.get(TypeId::of::<R>()).unwrap());
+        world.insert_resource(registry);
+        let comp_r = world.register_component::<R>();
+
+        let e1 = world.spawn(AuthoritativeHistory::default()).id();
+
+        // Write R(1) and R(3) to e1 for tick 0 and 2 respectively
+        world.entity_scope(e1, |e| {
+            write_reflected_history_internal(comp_r, e, r_tick(0), &R(1), frames);
+            write_reflected_history_internal(comp_r, e, r_tick(2), &R(3), frames);
+        });
+
+        use Missing as M;
+
+        let e = world.entity(e1);
+        let hist = e.get::<AuthoritativeHistory>().unwrap();
+        for (i, v) in [Value(R(1)), M, Value(R(3)), M].iter_enumerate() {
+            let stored = hist.get(&comp_r).unwrap().get(i as u32);
+            assert_eq!(
+                v,
+                stored.deref::<Box<dyn Reflect>>().map(|value| value
+                    .reflect_clone()
+                    .unwrap()
+                    .take::<R>()
+                    .unwrap())
+            );
+        }
+    }
+
     #[test]
     fn drop_once_on_success() {
         let mut world = World::new();
@@ -7,6 +7,7 @@ use bevy::{
 };
 
 use super::component::HistoryComponent;
+use crate::RollbackLoading;
 
 #[derive(Clone, Debug)]
 pub struct InsertBatch {
@@ -28,11 +29,26 @@ impl InsertBatch {
         self.ids.is_empty()
     }
 
+    #[cfg(test)]
     pub fn push(
         &mut self,
         comp_id: ComponentId,
         comp: &HistoryComponent,
         write_fn: impl FnOnce(PtrMut),
+    ) {
+        self.try_push(comp_id, comp, |ptr| {
+            write_fn(ptr);
+            true
+        });
+    }
+
+    /// Push a component whose write may fail, the component is left out of the batch
+    /// if `write_fn` returns false
+    pub fn try_push(
+        &mut self,
+        comp_id: ComponentId,
+        comp: &HistoryComponent,
+        write_fn: impl FnOnce(PtrMut) -> bool,
     ) {
         self.ids.push(comp_id);
         if comp.size() == 0 {
@@ -50,13 +66,19 @@ impl InsertBatch {
         let grow = comp.size() + extra_offset;
         let offset = self.data.len() + extra_offset;
 
+        let len = self.data.len();
         self.offsets.push(offset);
         self.data.extend((0..grow).map(|_| 0));
-        write_fn(unsafe {
+        let written = write_fn(unsafe {
             PtrMut::new(NonNull::new_unchecked(
                 (&mut self.data[offset..] as *mut [u8]).cast(),
             ))
         });
+        if !written {
+            self.ids.pop();
+            self.offsets.pop();
+            self.data.truncate(len);
+        }
     }
 
     pub fn clear(&mut self) {
@@ -68,15 +90,31 @@ impl InsertBatch {
 
 impl EntityCommand for InsertBatch {
     fn apply(mut self, mut entity: EntityWorldMut) {
-        let iter = self.offsets.iter().map(|&offset| {
-            let ptr = unsafe {
-                PtrMut::new(NonNull::new_unchecked(
-                    (&mut self.data[offset..] as *mut [u8]).cast(),
-                ))
-            };
-            unsafe { ptr.promote() }
+        while_loading(&mut entity, |entity| {
+            let iter = self.offsets.iter().map(|&offset| {
+                let ptr = unsafe {
+                    PtrMut::new(NonNull::new_unchecked(
+                        (&mut self.data[offset..] as *mut [u8]).cast(),
+                    ))
+                };
+                unsafe { ptr.promote() }
+            });
+            unsafe { entity.insert_by_ids(&self.ids, iter) };
         });
-        unsafe { entity.insert_by_ids(&self.ids, iter) };
+    }
+}
+
+/// Run structural changes with the [`RollbackLoading`] resource present,
+/// so hooks and observers can tell them apart from gameplay changes
+fn while_loading(entity: &mut EntityWorldMut, f: impl FnOnce(&mut EntityWorldMut)) {
+    let nested = entity.world_scope(|world| {
+        let nested = world.contains_resource::<RollbackLoading>();
+        world.insert_resource(RollbackLoading);
+        nested
+    });
+    f(entity);
+    if !nested {
+        entity.world_scope(|world| world.remove_resource::<RollbackLoading>());
     }
 }
 
@@ -107,9 +145,11 @@ impl RemoveBatch {
 
 impl EntityCommand for RemoveBatch {
     fn apply(self, mut entity: EntityWorldMut) {
-        for id in self.ids {
-            entity.remove_by_id(id);
-        }
+        while_loading(&mut entity, |entity| {
+            for id in self.ids {
+                entity.remove_by_id(id);
+            }
+        });
     }
 }
 
@@ -117,7 +157,8 @@ impl EntityCommand for RemoveBatch {
 mod tests {
     use crate::history::component::HistoryComponent;
 
-    use super::{super::test_utils::*, InsertBatch};
+    use super::{super::test_utils::*, InsertBatch, RemoveBatch};
+    use crate::RollbackLoading;
     use bevy::{ecs::system::EntityCommand, prelude::*};
 
     #[test]
@@ -152,4 +193,41 @@ mod tests {
         let archetypes_after = world.archetypes().len();
         assert_eq!(archetypes_before + 1, archetypes_after);
     }
+
+    #[test]
+    fn marks_structural_changes_as_loading() {
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct Loading(Vec<bool>);
+
+        let mut world = World::new();
+        world.init_resource::<Loading>();
+        world.add_observer(
+            |_: Trigger<OnInsert, A>,
+             loading: Option<Res<RollbackLoading>>,
+             mut seen: ResMut<Loading>| seen.push(loading.is_some()),
+        );
+        world.add_observer(
+            |_: Trigger<OnRemove, A>,
+             loading: Option<Res<RollbackLoading>>,
+             mut seen: ResMut<Loading>| seen.push(loading.is_some()),
+        );
+        let comp_a = world.register_component::<A>();
+
+        let mut batch = InsertBatch::new();
+        batch.push(comp_a, &HistoryComponent::new::<A>(), |ptr| {
+            *unsafe { ptr.deref_mut::<A>() } = A(5);
+        });
+        let e1 = world.spawn_empty().id();
+        batch.apply(world.entity_mut(e1));
+
+        let mut batch = RemoveBatch::new();
+        batch.push(comp_a);
+        batch.apply(world.entity_mut(e1));
+
+        // Gameplay changes are not marked
+        world.entity_mut(e1).insert(A(1));
+
+        assert_eq!(**world.resource::<Loading>(), [true, true, false]);
+        assert!(!world.contains_resource::<RollbackLoading>());
+    }
 }
@@ -1,27 +1,88 @@
 use std::{
     alloc::Layout,
+    any::TypeId,
+    fmt::Debug,
     mem::{ManuallyDrop, MaybeUninit},
     num::NonZero,
 };
 
 use bevy::{
+    ecs::{
+        component::ComponentInfo,
+        entity::{EntityMapper, MapEntities},
+    },
     prelude::*,
     ptr::{OwningPtr, Ptr, PtrMut},
+    reflect::{ReflectFromPtr, ReflectFromReflect, TypeRegistration, std_traits::ReflectDefault},
 };
+use serde::{Serialize, de::DeserializeOwned};
 
 #[derive(Clone)]
 pub struct HistoryComponent {
     layout: Layout,
-    store: unsafe fn(Ptr, PtrMut),
-    equal: unsafe fn(Ptr, Ptr) -> bool,
+    history_layout: Layout,
+    store: unsafe fn(&HistoryComponent, Ptr, PtrMut),
+    equal: unsafe fn(&HistoryComponent, Ptr, Ptr) -> bool,
     call_load: CallLoad,
     load: unsafe fn(),
     drop: Option<unsafe fn(OwningPtr)>,
+    debug: unsafe fn(&HistoryComponent, Ptr) -> String,
+    map_entities: Option<unsafe fn(PtrMut, &mut dyn EntityMapper)>,
+    storage: HistoryStorage,
+    reflect: Option<ReflectFns>,
+    #[cfg(debug_assertions)]
+    history_type: HistoryType,
+}
+
+/// How a component's values are stored in its history
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HistoryStorage {
+    /// Cloned values of the component
+    Value,
+    /// Serialized bytes
+    Serialized,
+    /// Boxed reflected values
+    Reflected,
+}
+
+/// Type information recorded in debug builds, used to validate type-erased accesses
+#[cfg(debug_assertions)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HistoryType {
+    type_id: TypeId,
+    type_name: &'static str,
+    history_layout: Layout,
+}
+
+#[cfg(debug_assertions)]
+impl HistoryType {
+    fn of<T: 'static>(history_layout: Layout) -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            history_layout,
+        }
+    }
 }
 
 pub type LoadFn<T> = fn(Option<&T>, Option<&T>, ExistingOrUninit<T>, Commands, entity: Entity);
-type CallLoad =
-    unsafe fn(unsafe fn(), Option<Ptr>, Option<Ptr>, ErasedExistingOrUninit, Commands, Entity);
+type CallLoad = unsafe fn(
+    &HistoryComponent,
+    Option<Ptr>,
+    Option<Ptr>,
+    ErasedExistingOrUninit,
+    Commands,
+    Entity,
+) -> bool;
+
+/// Reflection data used by components registered through the type registry
+#[derive(Clone)]
+struct ReflectFns {
+    type_path: &'static str,
+    type_id: TypeId,
+    from_ptr: ReflectFromPtr,
+    from_reflect: ReflectFromReflect,
+}
 
 impl HistoryComponent {
     /// Get the size of the component
@@ -34,22 +95,83 @@ impl HistoryComponent {
         self.layout
     }
 
+    /// Get how values of this component are stored
+    pub fn storage(&self) -> HistoryStorage {
+        self.storage
+    }
+
+    /// Get the type information used to validate accesses to histories of this component
+    #[cfg(debug_assertions)]
+    pub fn history_type(&self) -> HistoryType {
+        self.history_type
+    }
+
+    /// Check that a history was created for this component, only checked in debug builds
+    #[track_caller]
+    pub fn check_history(&self, history: &super::component_history::ComponentHistory) {
+        #[cfg(debug_assertions)]
+        assert!(
+            history.history_type() == self.history_type,
+            "History type mismatch, a history of {} is accessed as {}",
+            history.history_type().type_name,
+            self.history_type.type_name,
+        );
+        #[cfg(not(debug_assertions))]
+        let _ = history;
+    }
+
+    /// Check that a component in the world matches this component, only checked in debug builds
+    #[track_caller]
+    pub fn check_component(&self, info: &ComponentInfo) {
+        #[cfg(debug_assertions)]
+        assert!(
+            info.type_id() == Some(self.history_type.type_id) && info.layout() == self.layout,
+            "Component type mismatch, {} is registered as {}",
+            info.name(),
+            self.history_type.type_name,
+        );
+        #[cfg(not(debug_assertions))]
+        let _ = info;
+    }
+
     /// Call the component's store function
-    /// SAFETY: The types of `src` and `dst` point to MUST match this component's type
+    /// SAFETY: `src` MUST point to this component's type, `dst` MUST point to its history type
     pub unsafe fn store(&self, src: Ptr, dst: PtrMut) {
         unsafe {
-            (self.store)(src, dst);
+            (self.store)(self, src, dst);
         }
     }
 
-    /// Call the component's equal function
-    /// SAFETY: The types of `a` and `b` point to MUST match this component's type
-    pub unsafe fn equal(&self, a: Ptr, b: Ptr) -> bool {
-        unsafe { (self.equal)(a, b) }
+    /// Format a stored value for debugging
+    /// SAFETY: `stored` MUST point to this component's history type
+    pub unsafe fn debug_stored(&self, stored: Ptr) -> String {
+        unsafe { (self.debug)(self, stored) }
     }
 
-    /// Call the component's load function targeting uninitialized memory
-    /// SAFETY: The types of `authoritative`, `predicted`, and `dst` point to MUST match this component's type
+    /// Call the component's equal function, comparing a stored value to a component
+    /// SAFETY: `stored` MUST point to this component's history type, `value` MUST point to its type
+    pub unsafe fn equal(&self, stored: Ptr, value: Ptr) -> bool {
+        unsafe { (self.equal)(self, stored, value) }
+    }
+
+    /// Check if stored values of this component hold entities that need to be mapped
+    pub fn maps_entities(&self) -> bool {
+        self.map_entities.is_some()
+    }
+
+    /// Map the entities held by a stored value, does nothing for components registered
+    /// without [`MapEntities`]
+    /// SAFETY: `stored` MUST point to this component's history type
+    pub unsafe fn map_entities(&self, stored: PtrMut, mapper: &mut dyn EntityMapper) {
+        if let Some(map_entities) = self.map_entities {
+            unsafe { map_entities(stored, mapper) };
+        }
+    }
+
+    /// Call the component's load function targeting uninitialized memory,
+    /// returns false if no value could be written
+    /// SAFETY: `authoritative` and `predicted` MUST point to this component's history type,
+    /// `dst` MUST point to its type
     pub unsafe fn load_to_uninit(
         &self,
         authoritative: Option<Ptr>,
@@ -57,21 +179,23 @@ impl HistoryComponent {
         dst: PtrMut,
         commands: Commands,
         entity: Entity,
-    ) {
+    ) -> bool {
         unsafe {
             (self.call_load)(
-                self.load,
+                self,
                 authoritative,
                 predicted,
                 ErasedExistingOrUninit::Uninit(dst),
                 commands,
                 entity,
-            );
+            )
         }
     }
 
-    /// Call the component's load function targeting an existing value
-    /// SAFETY: The types of `authoritative`, `predicted`, and `dst` point to MUST match this component's type
+    /// Call the component's load function targeting an existing value,
+    /// returns false if no value could be written
+    /// SAFETY: `authoritative` and `predicted` MUST point to this component's history type,
+    /// `dst` MUST point to its type
     // TODO:
     #[allow(dead_code)]
     pub unsafe fn load_to_component(
@@ -81,33 +205,34 @@ impl HistoryComponent {
         dst: PtrMut,
         commands: Commands,
         entity: Entity,
-    ) {
+    ) -> bool {
         unsafe {
             (self.call_load)(
-                self.load,
+                self,
                 authoritative,
                 predicted,
                 ErasedExistingOrUninit::Existing(dst),
                 commands,
                 entity,
-            );
+            )
         }
     }
 
-    pub fn new<T: Clone + PartialEq>() -> Self {
+    pub fn new<T: Clone + PartialEq + Debug + 'static>() -> Self {
         Self::new_internal::<T>(
             |_, auth: Option<Ptr>, pred, dst, _, _| unsafe {
                 dst.deref::<T>()
                     .write(auth.or(pred).unwrap().deref::<T>().clone());
+                true
             },
             || {},
         )
     }
 
-    pub fn with_load<T: Clone + PartialEq>(load_fn: LoadFn<T>) -> Self {
+    pub fn with_load<T: Clone + PartialEq + Debug + 'static>(load_fn: LoadFn<T>) -> Self {
         Self::new_internal::<T>(
-            |load, auth, pred, dst, commands, entity| {
-                let load = unsafe { std::mem::transmute::<unsafe fn(), LoadFn<T>>(load) };
+            |this, auth, pred, dst, commands, entity| {
+                let load = unsafe { std::mem::transmute::<unsafe fn(), LoadFn<T>>(this.load) };
                 (load)(
                     auth.map(|v| unsafe { v.deref::<T>() }),
                     pred.map(|v| unsafe { v.deref::<T>() }),
@@ -115,15 +240,55 @@ impl HistoryComponent {
                     commands,
                     entity,
                 );
+                true
             },
             unsafe { std::mem::transmute::<LoadFn<T>, unsafe fn()>(load_fn) },
         )
     }
 
-    fn new_internal<T: Clone + PartialEq>(call_load: CallLoad, load: unsafe fn()) -> Self {
+    /// Map the entities held by stored values with the component's [`MapEntities`] implementation.
+    /// Only components storing cloned values can be mapped.
+    pub fn with_map_entities<T: MapEntities + 'static>(self) -> Self {
+        self.with_map_fn::<T>(|stored, mut mapper| unsafe {
+            stored.deref_mut::<T>().map_entities(&mut mapper);
+        })
+    }
+
+    /// Map the entities held by stored values with [`Component::map_entities`], which is derived
+    /// for fields marked with `#[entities]`, like the ones of relationships
+    pub fn with_component_entities<T: Component>(self) -> Self {
+        self.with_map_fn::<T>(|stored, mut mapper| unsafe {
+            T::map_entities(stored.deref_mut::<T>(), &mut mapper);
+        })
+    }
+
+    fn with_map_fn<T: 'static>(
+        mut self,
+        map_entities: unsafe fn(PtrMut, &mut dyn EntityMapper),
+    ) -> Self {
+        assert_eq!(
+            HistoryStorage::Value,
+            self.storage,
+            "only histories of cloned values can map entities"
+        );
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            TypeId::of::<T>(),
+            self.history_type.type_id,
+            "entity mapping registered for the wrong type"
+        );
+        self.map_entities = Some(map_entities);
+        self
+    }
+
+    fn new_internal<T: Clone + PartialEq + Debug + 'static>(
+        call_load: CallLoad,
+        load: unsafe fn(),
+    ) -> Self {
         Self {
             layout: Layout::new::<T>(),
-            store: |src, dst| {
+            history_layout: Layout::new::<T>(),
+            store: |_, src, dst| {
                 // TODO: Rethink this and the write APIs to ensure our usage is sound and doesn't leak memory
                 let value = ManuallyDrop::new(unsafe { src.deref::<T>() }.clone());
                 unsafe {
@@ -134,21 +299,209 @@ impl HistoryComponent {
                     );
                 }
             },
-            equal: |a, b| unsafe { a.deref::<T>() == b.deref::<T>() },
+            equal: |_, a, b| unsafe { a.deref::<T>() == b.deref::<T>() },
             call_load,
             load,
             drop: Some(|ptr| unsafe { ptr.drop_as::<T>() }),
+            debug: |_, stored| format!("{:?}", unsafe { stored.deref::<T>() }),
+            map_entities: None,
+            storage: HistoryStorage::Value,
+            reflect: None,
+            #[cfg(debug_assertions)]
+            history_type: HistoryType::of::<T>(Layout::new::<T>()),
+        }
+    }
+
+    /// Construct a component that stores its history as serialized bytes.
+    /// Values that fail to serialize are logged and stored as empty bytes,
+    /// values that fail to deserialize are logged and not loaded.
+    pub fn serialized<T: Serialize + DeserializeOwned + Debug + 'static>() -> Self {
+        Self {
+            layout: Layout::new::<T>(),
+            history_layout: Layout::new::<Vec<u8>>(),
+            store: |_, src, dst| {
+                let bytes = serialize_value(unsafe { src.deref::<T>() }).unwrap_or_else(|e| {
+                    error!("failed to serialize {}: {e}", std::any::type_name::<T>());
+                    Vec::new()
+                });
+                let bytes = ManuallyDrop::new(bytes);
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        (&bytes as *const ManuallyDrop<Vec<u8>>).cast(),
+                        dst.as_ptr(),
+                        size_of::<Vec<u8>>(),
+                    );
+                }
+            },
+            equal: |_, stored, value| unsafe {
+                serialize_value(value.deref::<T>())
+                    .is_ok_and(|bytes| *stored.deref::<Vec<u8>>() == bytes)
+            },
+            call_load: |_, auth: Option<Ptr>, pred, dst, _, _| unsafe {
+                let bytes = auth.or(pred).unwrap().deref::<Vec<u8>>();
+                match deserialize_value(bytes) {
+                    Ok(value) => {
+                        dst.deref::<T>().write(value);
+                        true
+                    }
+                    Err(e) => {
+                        error!("failed to deserialize {}: {e}", std::any::type_name::<T>());
+                        false
+                    }
+                }
+            },
+            load: || {},
+            drop: Some(|ptr| unsafe { ptr.drop_as::<Vec<u8>>() }),
+            debug: |_, stored| {
+                let bytes = unsafe { stored.deref::<Vec<u8>>() };
+                match deserialize_value::<T>(bytes) {
+                    Ok(value) => format!("{value:?}"),
+                    Err(e) => format!("<{e}>"),
+                }
+            },
+            map_entities: None,
+            storage: HistoryStorage::Serialized,
+            reflect: None,
+            #[cfg(debug_assertions)]
+            history_type: HistoryType::of::<T>(Layout::new::<Vec<u8>>()),
+        }
+    }
+
+    /// Construct a component from its type registration, storing its history as reflected values.
+    /// The registration MUST have [`ReflectFromPtr`] and [`ReflectFromReflect`] type data, and
+    /// `layout` MUST be the layout of the registered type. Values MUST support
+    /// [`PartialReflect::reflect_partial_eq`], which is checked here for types reflecting
+    /// `Default`.
+    pub fn reflected(registration: &TypeRegistration, layout: Layout) -> Self {
+        let type_path = registration.type_info().type_path();
+        let reflect = ReflectFns {
+            type_path,
+            type_id: registration.type_id(),
+            from_ptr: registration
+                .data::<ReflectFromPtr>()
+                .cloned()
+                .unwrap_or_else(|| panic!("{type_path} is missing ReflectFromPtr type data")),
+            from_reflect: registration
+                .data::<ReflectFromReflect>()
+                .cloned()
+                .unwrap_or_else(|| panic!("{type_path} is missing ReflectFromReflect type data")),
+        };
+
+        // Values without reflected `PartialEq` would always be considered changed
+        if let Some(default) = registration.data::<ReflectDefault>() {
+            let value = default.default();
+            assert!(
+                value
+                    .reflect_partial_eq(value.as_partial_reflect())
+                    .is_some(),
+                "{type_path} doesn't reflect PartialEq"
+            );
+        }
+
+        Self {
+            layout,
+            history_layout: Layout::new::<Box<dyn Reflect>>(),
+            store: |this, src, dst| {
+                let reflect = this.reflect.as_ref().unwrap();
+                let value = unsafe { reflect.from_ptr.as_reflect(src) };
+                let value = ManuallyDrop::new(reflect.clone_value(value));
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        (&value as *const ManuallyDrop<Box<dyn Reflect>>).cast(),
+                        dst.as_ptr(),
+                        size_of::<Box<dyn Reflect>>(),
+                    );
+                }
+            },
+            equal: |this, stored, value| {
+                let reflect = this.reflect.as_ref().unwrap();
+                let stored = unsafe { stored.deref::<Box<dyn Reflect>>() };
+                let value = unsafe { reflect.from_ptr.as_reflect(value) };
+                (**stored)
+                    .reflect_partial_eq(value.as_partial_reflect())
+                    .unwrap_or_else(|| panic!("{} doesn't reflect PartialEq", reflect.type_path))
+            },
+            call_load: |this, auth: Option<Ptr>, pred, dst, _, _| {
+                let reflect = this.reflect.as_ref().unwrap();
+                let stored = unsafe { auth.or(pred).unwrap().deref::<Box<dyn Reflect>>() };
+                match dst {
+                    ErasedExistingOrUninit::Existing(dst) => {
+                        let dst = unsafe { reflect.from_ptr.as_reflect_mut(dst) };
+                        dst.apply((**stored).as_partial_reflect());
+                    }
+                    ErasedExistingOrUninit::Uninit(dst) => {
+                        let value = reflect.clone_value(&**stored);
+                        // SAFETY: `clone_value` checks the type, and the layout matches the type
+                        unsafe { move_boxed(value, dst) };
+                    }
+                }
+                true
+            },
+            load: || {},
+            drop: Some(|ptr| unsafe { ptr.drop_as::<Box<dyn Reflect>>() }),
+            debug: |_, stored| format!("{:?}", unsafe { stored.deref::<Box<dyn Reflect>>() }),
+            map_entities: None,
+            storage: HistoryStorage::Reflected,
+            #[cfg(debug_assertions)]
+            history_type: HistoryType {
+                type_id: reflect.type_id,
+                type_name: reflect.type_path,
+                history_layout: Layout::new::<Box<dyn Reflect>>(),
+            },
+            reflect: Some(reflect),
+        }
+    }
+}
+
+impl ReflectFns {
+    /// Clone a reflected value into a boxed value of the registered type
+    fn clone_value(&self, value: &dyn Reflect) -> Box<dyn Reflect> {
+        let cloned = value
+            .reflect_clone()
+            .ok()
+            .or_else(|| self.from_reflect.from_reflect(value.as_partial_reflect()))
+            .unwrap_or_else(|| panic!("failed to clone reflected {}", self.type_path));
+        assert_eq!(
+            self.type_id,
+            cloned.as_any().type_id(),
+            "cloned reflected value is not a {}",
+            self.type_path,
+        );
+        cloned
+    }
+}
+
+/// Move the value out of a box into `dst`
+/// SAFETY: `dst` MUST point to uninitialized memory matching the layout of the boxed value
+unsafe fn move_boxed(value: Box<dyn Reflect>, dst: PtrMut) {
+    let layout = Layout::for_value(&*value);
+    let raw = Box::into_raw(value);
+    unsafe {
+        std::ptr::copy_nonoverlapping(raw.cast::<u8>(), dst.as_ptr(), layout.size());
+        if layout.size() != 0 {
+            std::alloc::dealloc(raw.cast(), layout);
         }
     }
 }
 
+/// Serialize a value to the format used by serialized histories
+pub(crate) fn serialize_value<T: Serialize>(value: &T) -> postcard::Result<Vec<u8>> {
+    postcard::to_allocvec(value)
+}
+
+/// Deserialize a value stored in a serialized history
+pub(crate) fn deserialize_value<T: DeserializeOwned>(bytes: &[u8]) -> postcard::Result<T> {
+    postcard::from_bytes(bytes)
+}
+
 impl super::sparse_blob_deque::SparseBlobDeque {
     pub(super) fn from_component(component: &HistoryComponent, size: NonZero<u8>) -> Self {
         // SAFETY: We call this using a valid HistoryComponent
-        unsafe { Self::new(component.layout, component.drop, size) }
+        unsafe { Self::new(component.history_layout, component.drop, size) }
     }
 
-    pub(super) fn from_type<T: Clone + PartialEq>(size: NonZero<u8>) -> Self {
+    #[cfg(test)]
+    pub(super) fn from_type<T: Clone + PartialEq + Debug + 'static>(size: NonZero<u8>) -> Self {
         Self::from_component(&HistoryComponent::new::<T>(), size)
     }
 }
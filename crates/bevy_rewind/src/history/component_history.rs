@@ -1,4 +1,6 @@
 use super::component::HistoryComponent;
+#[cfg(debug_assertions)]
+use super::component::HistoryType;
 use super::sparse_blob_deque::SparseBlobDeque;
 
 use std::num::NonZero;
@@ -19,6 +21,8 @@ pub struct ComponentHistory {
     removed_mask: u64,
     list: SparseBlobDeque,
     last_tick: u32,
+    #[cfg(debug_assertions)]
+    history_type: HistoryType,
 }
 
 impl core::fmt::Debug for ComponentHistory {
@@ -113,21 +117,25 @@ impl ComponentHistory {
             removed_mask: 0,
             list: SparseBlobDeque::from_component(component, size),
             last_tick: 0,
+            #[cfg(debug_assertions)]
+            history_type: component.history_type(),
         }
     }
 
-    pub(crate) fn from_type<T: Clone + PartialEq>(size: NonZero<u8>) -> Self {
-        Self {
-            removed_mask: 0,
-            list: SparseBlobDeque::from_type::<T>(size),
-            last_tick: 0,
-        }
+    /// The type information of the component this history was created for
+    #[cfg(debug_assertions)]
+    pub fn history_type(&self) -> HistoryType {
+        self.history_type
     }
 
     pub fn len(&self) -> usize {
         self.list.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
     #[cfg(test)]
     pub fn stored_items(&self) -> usize {
         self.list.stored_items()
@@ -138,6 +146,10 @@ impl ComponentHistory {
         self.last_tick = last_tick;
     }
 
+    pub fn last_tick(&self) -> u32 {
+        self.last_tick
+    }
+
     pub fn first_tick(&self) -> u32 {
         self.last_tick.saturating_sub(
             63u32.saturating_sub((self.removed_mask | self.list.mask()).leading_zeros()),
@@ -164,6 +176,11 @@ impl ComponentHistory {
         }
     }
 
+    /// Call a function on each stored value
+    pub fn for_each_value_mut(&mut self, f: impl FnMut(PtrMut)) {
+        self.list.for_each_mut(f);
+    }
+
     pub fn get_latest<'a>(&'a self, tick: u32) -> TickData<Ptr<'a>> {
         let ago = self.last_tick.saturating_sub(tick) as usize;
         if ago >= self.len() {
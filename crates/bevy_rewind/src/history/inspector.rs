@@ -0,0 +1,268 @@
+use super::{
+    AuthoritativeHistory, PredictedHistory, RollbackRegistry,
+    component::HistoryStorage,
+    component_history::{self, ComponentHistory},
+};
+use crate::TickData;
+
+use std::{fmt::Display, marker::PhantomData};
+
+use bevy::{
+    ecs::{
+        component::{ComponentId, Components},
+        entity_disabling::Disabled,
+        system::SystemParam,
+    },
+    prelude::*,
+};
+use bevy_replicon::{
+    client::{confirm_history::ConfirmHistory, server_mutate_ticks::ServerMutateTicks},
+    shared::replicon_tick::RepliconTick,
+};
+
+/// A system param to read the stored histories of a registered component, useful for debug views
+/// and assertions in tests.
+///
+/// Components stored as values or reflected values can be inspected, serialized components are
+/// rejected with [`InspectError::Serialized`].
+#[derive(SystemParam)]
+pub struct HistoryInspector<'w, 's, T: Component> {
+    histories: Query<
+        'w,
+        's,
+        (
+            Option<&'static PredictedHistory>,
+            Option<&'static AuthoritativeHistory>,
+            Option<&'static ConfirmHistory>,
+        ),
+        Or<(With<Disabled>, Without<Disabled>)>,
+    >,
+    registry: Res<'w, RollbackRegistry>,
+    components: &'w Components,
+    global_confirm: Option<Res<'w, ServerMutateTicks>>,
+    phantom: PhantomData<T>,
+}
+
+/// An error while inspecting a component's history
+#[derive(Debug, PartialEq, Eq)]
+pub enum InspectError {
+    /// The history is stored as serialized bytes, which can't be read as the component
+    Serialized,
+}
+
+impl Display for InspectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InspectError::Serialized => write!(f, "serialized histories can't be inspected"),
+        }
+    }
+}
+
+impl std::error::Error for InspectError {}
+
+impl<T: Component> HistoryInspector<'_, '_, T> {
+    /// Get the predicted value of the entity's component at the specified tick.
+    /// Because unchanged values are not stored, this is the latest value stored at or before it.
+    pub fn predicted(
+        &self,
+        entity: Entity,
+        tick: RepliconTick,
+    ) -> Result<TickData<&T>, InspectError> {
+        let component = self.component()?;
+        let Ok((Some(predicted), _, _)) = self.histories.get(entity) else {
+            return Ok(TickData::Missing);
+        };
+        Ok(Self::read(component, |id| predicted.get(&id), tick))
+    }
+
+    /// Get the authoritative value of the entity's component received for the specified tick,
+    /// or the latest value received before it.
+    pub fn authoritative(
+        &self,
+        entity: Entity,
+        tick: RepliconTick,
+    ) -> Result<TickData<&T>, InspectError> {
+        let component = self.component()?;
+        let Ok((_, Some(authoritative), _)) = self.histories.get(entity) else {
+            return Ok(TickData::Missing);
+        };
+        Ok(Self::read(component, |id| authoritative.get(&id), tick))
+    }
+
+    /// Check if the server confirmed the entity's state for the specified tick,
+    /// either for this entity specifically or globally.
+    pub fn is_confirmed(&self, entity: Entity, tick: RepliconTick) -> bool {
+        let confirmed = self
+            .histories
+            .get(entity)
+            .ok()
+            .and_then(|(_, _, confirm)| confirm)
+            .is_some_and(|confirm| confirm.contains(tick));
+        confirmed
+            || self
+                .global_confirm
+                .as_ref()
+                .is_some_and(|global| global.contains_any(tick, tick))
+    }
+
+    /// Get the last tick for which the entity's state was confirmed
+    pub fn last_confirmed(&self, entity: Entity) -> Option<RepliconTick> {
+        let (_, _, confirm) = self.histories.get(entity).ok()?;
+        confirm.map(|confirm| confirm.last_tick())
+    }
+
+    /// Get the id and storage of the component, if it is registered
+    fn component(&self) -> Result<Option<(ComponentId, HistoryStorage)>, InspectError> {
+        let Some(id) = self.components.component_id::<T>() else {
+            return Ok(None);
+        };
+        let Some(&index) = self.registry.ids.get(&id) else {
+            return Ok(None);
+        };
+        match self.registry.components[index].storage() {
+            HistoryStorage::Serialized => Err(InspectError::Serialized),
+            storage => Ok(Some((id, storage))),
+        }
+    }
+
+    fn read<'a>(
+        component: Option<(ComponentId, HistoryStorage)>,
+        history: impl FnOnce(ComponentId) -> Option<&'a ComponentHistory>,
+        tick: RepliconTick,
+    ) -> TickData<&'a T> {
+        let Some((history, storage)) =
+            component.and_then(|(id, storage)| Some((history(id)?, storage)))
+        else {
+            return TickData::Missing;
+        };
+        match history.get_latest(tick.get()) {
+            // SAFETY: The history belongs to T's ComponentId and is stored as `storage`
+            component_history::TickData::Value(ptr) => TickData::Value(match storage {
+                HistoryStorage::Reflected => unsafe { ptr.deref::<Box<dyn Reflect>>() }
+                    .downcast_ref::<T>()
+                    .unwrap(),
+                _ => unsafe { ptr.deref::<T>() },
+            }),
+            component_history::TickData::Removed => TickData::Removed,
+            component_history::TickData::Missing => TickData::Missing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{RollbackRegistry, component_history::TickData as Stored, test_utils::*},
+        HistoryInspector, InspectError,
+    };
+    use crate::{TickData, history::PredictedHistory};
+
+    use std::any::TypeId;
+
+    use bevy::{ecs::system::RunSystemOnce, prelude::*, reflect::TypeRegistry};
+    use bevy_replicon::client::server_mutate_ticks::ServerMutateTicks;
+
+    #[test]
+    fn inspect_histories() {
+        let mut world = World::new();
+        world.init_resource::<ServerMutateTicks>();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(&mut world);
+        world.insert_resource(registry);
+        let comp_a = world.register_component::<A>();
+
+        let e1 = world
+            .spawn((
+                pred_history(0, comp_a, [a(1), a(2), Stored::Removed]),
+                auth_history(1, comp_a, [a(5)]),
+                confirm_history([1]),
+            ))
+            .id();
+        let e2 = world.spawn_empty().id();
+
+        world
+            .resource_mut::<ServerMutateTicks>()
+            .confirm(r_tick(3), 1);
+
+        world
+            .run_system_once(move |inspector: HistoryInspector<A>| {
+                assert_eq!(
+                    Ok(TickData::Value(&A(1))),
+                    inspector.predicted(e1, r_tick(0))
+                );
+                assert_eq!(
+                    Ok(TickData::Value(&A(2))),
+                    inspector.predicted(e1, r_tick(1))
+                );
+                assert_eq!(Ok(TickData::Removed), inspector.predicted(e1, r_tick(2)));
+
+                assert_eq!(
+                    Ok(TickData::Missing),
+                    inspector.authoritative(e1, r_tick(0))
+                );
+                assert_eq!(
+                    Ok(TickData::Value(&A(5))),
+                    inspector.authoritative(e1, r_tick(1))
+                );
+                // Later ticks fall back to the latest received value
+                assert_eq!(
+                    Ok(TickData::Value(&A(5))),
+                    inspector.authoritative(e1, r_tick(2))
+                );
+
+                assert!(inspector.is_confirmed(e1, r_tick(1)));
+                assert!(!inspector.is_confirmed(e1, r_tick(2)));
+                // Globally confirmed ticks are confirmed for all entities
+                assert!(inspector.is_confirmed(e1, r_tick(3)));
+                assert!(inspector.is_confirmed(e2, r_tick(3)));
+
+                // Entities without histories return Missing
+                assert_eq!(Ok(TickData::Missing), inspector.predicted(e2, r_tick(0)));
+                assert_eq!(
+                    Ok(TickData::Missing),
+                    inspector.authoritative(e2, r_tick(0))
+                );
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn inspect_stored_types() {
+        let mut world = World::new();
+        world.init_resource::<ServerMutateTicks>();
+
+        let mut types = TypeRegistry::new();
+        types.register::<R>();
+        let mut registry = RollbackRegistry::default();
+        registry.register_reflect(&mut world, types.get(TypeId::of::<R>()).unwrap());
+        registry.register_serialized::<S>(&mut world);
+        let comp_r = world.register_component::<R>();
+        let component = registry.components[*registry.ids.get(&comp_r).unwrap()].clone();
+        world.insert_resource(registry);
+
+        let mut pred_hist = PredictedHistory::default();
+        pred_hist.insert(
+            comp_r,
+            stored_comp_history(&component, 0, [Stored::Value(R(4))]),
+        );
+        let e1 = world.spawn(pred_hist).id();
+
+        world
+            .run_system_once(move |inspector: HistoryInspector<R>| {
+                assert_eq!(
+                    Ok(TickData::Value(&R(4))),
+                    inspector.predicted(e1, r_tick(0))
+                );
+            })
+            .unwrap();
+        world
+            .run_system_once(move |inspector: HistoryInspector<S>| {
+                assert_eq!(
+                    Err(InspectError::Serialized),
+                    inspector.predicted(e1, r_tick(0))
+                );
+            })
+            .unwrap();
+    }
+}
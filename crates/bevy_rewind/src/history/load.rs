@@ -5,7 +5,7 @@ use super::{
     component_history::TickData,
     predicted::PredictedHistory,
 };
-use crate::{LoadFrom, Predicted, RollbackLoadSet, RollbackSchedule};
+use crate::{LoadFrom, Predicted, Resimulated, RollbackLoadSet, RollbackSchedule};
 
 use bevy::{
     ecs::{
@@ -49,6 +49,7 @@ fn load_and_clear_prediction(
     previous_tick: Res<LoadFrom>,
     global_confirm: Res<ServerMutateTicks>,
     entities: &Entities,
+    resimulated: Resimulated,
 ) {
     let mut inserts = InsertBatch::new();
     let mut load_queue = CommandQueue::default();
@@ -56,14 +57,20 @@ fn load_and_clear_prediction(
 
     // TODO: Can we par_iter this?
     for (entity, mut predicted, maybe_authoritative) in q.iter_mut() {
+        if !resimulated.contains(entity) {
+            continue;
+        }
         let mut load_commands = Commands::new_from_entities(&mut load_queue, entities);
         for (&comp_id, pred_hist) in predicted.iter_mut() {
             let &reg_idx = registry.ids.get(&comp_id).unwrap();
             let component = registry.components.get(reg_idx).unwrap();
 
+            component.check_history(pred_hist);
+
             let auth = maybe_authoritative
                 .map(|(authoritative, confirmed)| {
                     if let Some(auth_hist) = authoritative.get(&comp_id) {
+                        component.check_history(auth_hist);
                         let check_range = auth_hist.empty_after(previous_tick.get());
                         let end_tick = RepliconTick::new(previous_tick.get() + check_range);
                         if confirmed.contains_any(**previous_tick, end_tick)
@@ -90,14 +97,14 @@ fn load_and_clear_prediction(
                     continue;
                 }
                 (auth, pred) => {
-                    inserts.push(comp_id, component, |dst| unsafe {
+                    inserts.try_push(comp_id, component, |dst| unsafe {
                         component.load_to_uninit(
                             auth.value(),
                             pred.value(),
                             dst,
                             load_commands.reborrow(),
                             entity,
-                        );
+                        )
                     });
                 }
             }
@@ -136,6 +143,7 @@ fn load_confirmed_authoritative(
     previous_tick: Res<LoadFrom>,
     global_confirm: Res<ServerMutateTicks>,
     entities: &Entities,
+    resimulated: Resimulated,
 ) {
     let mut inserts = InsertBatch::new();
     let mut load_queue = CommandQueue::default();
@@ -143,10 +151,14 @@ fn load_confirmed_authoritative(
 
     // TODO: Can we par_iter this?
     for (entity, authoritative, confirmed) in q.iter_mut() {
+        if !resimulated.contains(entity.id()) {
+            continue;
+        }
         let mut load_commands = Commands::new_from_entities(&mut load_queue, entities);
         for (&comp_id, auth_hist) in authoritative.iter() {
             let &reg_idx = registry.ids.get(&comp_id).unwrap();
             let component = registry.components.get(reg_idx).unwrap();
+            component.check_history(auth_hist);
 
             let check_range = auth_hist.empty_after(previous_tick.get());
             let end_tick = RepliconTick::new(previous_tick.get() + check_range);
@@ -158,14 +170,14 @@ fn load_confirmed_authoritative(
 
             match auth_hist.get_latest(previous_tick.get()) {
                 TickData::Value(value) => {
-                    inserts.push(comp_id, component, |dst| unsafe {
+                    inserts.try_push(comp_id, component, |dst| unsafe {
                         component.load_to_uninit(
                             Some(value),
-                            entity.get_by_id(comp_id),
+                            None,
                             dst,
                             load_commands.reborrow(),
                             entity.id(),
-                        );
+                        )
                     });
                     continue;
                 }
@@ -203,12 +215,16 @@ fn reinsert_predicted(
     registry: Res<RollbackRegistry>,
     previous_tick: Res<LoadFrom>,
     entities: &Entities,
+    resimulated: Resimulated,
 ) {
     let mut inserts = InsertBatch::new();
     let mut load_queue = CommandQueue::default();
 
     // TODO: Can we par_iter this?
     for (entity, archetype, predicted, authoritative) in q.iter_mut() {
+        if !resimulated.contains(entity) {
+            continue;
+        }
         let mut load_commands = Commands::new_from_entities(&mut load_queue, entities);
         for (&comp_id, pred_hist) in predicted.iter() {
             if archetype.contains(comp_id) {
@@ -224,9 +240,10 @@ fn reinsert_predicted(
 
             let &reg_idx = registry.ids.get(&comp_id).unwrap();
             let component = registry.components.get(reg_idx).unwrap();
+            component.check_history(pred_hist);
 
-            inserts.push(comp_id, component, |dst| unsafe {
-                component.load_to_uninit(None, Some(value), dst, load_commands.reborrow(), entity);
+            inserts.try_push(comp_id, component, |dst| unsafe {
+                component.load_to_uninit(None, Some(value), dst, load_commands.reborrow(), entity)
             });
         }
 
@@ -246,6 +263,8 @@ fn reinsert_predicted(
 mod tests {
     use crate::{LoadFrom, Predicted};
 
+    use std::any::TypeId;
+
     use super::{
         super::{
             component_history::TickData, load::load_confirmed_authoritative,
@@ -256,12 +275,13 @@ mod tests {
     use bevy::{
         ecs::{component::ComponentId, system::ScheduleSystem},
         prelude::*,
+        reflect::TypeRegistry,
     };
     use bevy_replicon::{
         client::server_mutate_ticks::ServerMutateTicks, shared::replicon_tick::RepliconTick,
     };
 
-    fn init_app<C: Component + Clone + PartialEq, M>(
+    fn init_app<C: Component + Clone + PartialEq + std::fmt::Debug, M>(
         load_from: u32,
         system: impl IntoScheduleConfigs<ScheduleSystem, M>,
     ) -> (App, ComponentId) {
@@ -540,6 +560,173 @@ mod tests {
         assert_eq!(Some(&A(4)), e.get::<A>());
     }
 
+    #[test]
+    fn load_immutable_predicted() {
+        let (mut app, comp_i) = init_app::<I, _>(1, load_and_clear_prediction);
+
+        let pred_hist = pred_history(0, comp_i, [i(4), i(5)]);
+        let e1 = app.world_mut().spawn((Predicted, pred_hist, I(1))).id();
+
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct Replaced(Vec<I>);
+        app.init_resource::<Replaced>().add_observer(
+            |trigger: Trigger<OnReplace, I>, q: Query<&I>, mut replaced: ResMut<Replaced>| {
+                replaced.push(q.get(trigger.target()).unwrap().clone());
+            },
+        );
+
+        app.update();
+
+        // The old value should be replaced rather than mutated
+        let e = app.world().entity(e1);
+        assert_eq!(Some(&I(5)), e.get::<I>());
+        assert_eq!(vec![I(1)], **app.world().resource::<Replaced>());
+    }
+
+    #[test]
+    fn load_immutable_authoritative() {
+        let (mut app, comp_i) = init_app::<I, _>(0, load_and_clear_prediction);
+
+        let pred_hist = pred_history::<I>(0, comp_i, []);
+        let auth_hist = auth_history(0, comp_i, [i(5)]);
+        let confirm = confirm_history([0]); // The target tick is confirmed
+        let e1 = app
+            .world_mut()
+            .spawn((Predicted, pred_hist, auth_hist, confirm, I(1)))
+            .id();
+
+        app.update();
+
+        let e = app.world().entity(e1);
+        assert_eq!(Some(&I(5)), e.get::<I>());
+    }
+
+    #[test]
+    fn load_serialized_predicted() {
+        let mut app = App::new();
+        app.add_systems(Update, load_and_clear_prediction)
+            .init_resource::<ServerMutateTicks>()
+            .insert_resource(LoadFrom(RepliconTick::new(1)));
+
+        let mut registry = RollbackRegistry::default();
+        registry.register_serialized::<S>(app.world_mut());
+        app.insert_resource(registry);
+        let comp_s = app.world_mut().register_component::<S>();
+
+        let mut pred_hist = PredictedHistory::default();
+        pred_hist.insert(
+            comp_s,
+            serialized_comp_history(
+                0,
+                [TickData::Value(S(vec![4])), TickData::Value(S(vec![5, 6]))],
+            ),
+        );
+        let e1 = app
+            .world_mut()
+            .spawn((Predicted, pred_hist, S(vec![1])))
+            .id();
+
+        app.update();
+
+        let e = app.world().entity(e1);
+        assert_eq!(Some(&S(vec![5, 6])), e.get::<S>());
+    }
+
+    #[test]
+    fn skip_undeserializable_predicted() {
+        let mut app = App::new();
+        app.add_systems(Update, load_and_clear_prediction)
+            .init_resource::<ServerMutateTicks>()
+            .insert_resource(LoadFrom(RepliconTick::new(1)));
+
+        let mut registry = RollbackRegistry::default();
+        registry.register_serialized::<U>(app.world_mut());
+        app.insert_resource(registry);
+        let comp_u = app.world_mut().register_component::<U>();
+
+        let mut pred_hist = PredictedHistory::default();
+        pred_hist.insert(
+            comp_u,
+            serialized_comp_history(
+                0,
+                [
+                    TickData::Value(U::Number(4)),
+                    TickData::Value(U::Text("5".into())),
+                ],
+            ),
+        );
+        let e1 = app
+            .world_mut()
+            .spawn((Predicted, pred_hist, U::Number(1)))
+            .id();
+
+        app.update();
+
+        // The value can't be loaded, so the current one is kept
+        let e = app.world().entity(e1);
+        assert_eq!(Some(&U::Number(1)), e.get::<U>());
+    }
+
+    #[test]
+    fn load_reflected_predicted() {
+        let mut app = App::new();
+        app.add_systems(Update, load_and_clear_prediction)
+            .init_resource::<ServerMutateTicks>()
+            .insert_resource(LoadFrom(RepliconTick::new(1)));
+
+        let mut types = TypeRegistry::new();
+        types.register::<R>();
+        let registration = types.get(TypeId::of::<R>()).unwrap();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register_reflect(app.world_mut(), registration);
+        let comp_r = app.world_mut().register_component::<R>();
+        let component = registry.components[*registry.ids.get(&comp_r).unwrap()].clone();
+        app.insert_resource(registry);
+
+        let mut pred_hist = PredictedHistory::default();
+        pred_hist.insert(
+            comp_r,
+            stored_comp_history(
+                &component,
+                0,
+                [TickData::Value(R(4)), TickData::Value(R(5))],
+            ),
+        );
+        let e1 = app.world_mut().spawn((Predicted, pred_hist, R(1))).id();
+
+        app.update();
+
+        let e = app.world().entity(e1);
+        assert_eq!(Some(&R(5)), e.get::<R>());
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't reflect PartialEq")]
+    fn reject_reflected_without_partial_eq() {
+        let mut world = World::new();
+        let mut types = TypeRegistry::new();
+        types.register::<NoEq>();
+        let registration = types.get(TypeId::of::<NoEq>()).unwrap();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register_reflect(&mut world, registration);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "History type mismatch")]
+    fn detect_history_type_mismatch() {
+        let (mut app, comp_a) = init_app::<A, _>(0, load_and_clear_prediction);
+
+        // Store a history of C for A's ComponentId
+        let mut pred_hist = PredictedHistory::default();
+        pred_hist.insert(comp_a, comp_history(0, [TickData::Value(C(1, 2))]));
+        app.world_mut().spawn((Predicted, pred_hist, A(1)));
+
+        app.update();
+    }
+
     #[test]
     fn skip_unpredicted() {
         let (mut app, comp_a) = init_app::<A, _>(0, load_and_clear_prediction);
@@ -5,7 +5,7 @@ mod sparse_blob_deque;
 // Shared history types
 mod component;
 pub use component::{ExistingOrUninit, LoadFn};
-mod component_history;
+pub(crate) mod component_history;
 
 // Specific history types
 mod authoritative;
@@ -16,13 +16,42 @@ pub use predicted::PredictedHistory;
 mod batch;
 mod load;
 
+mod inspector;
+pub use inspector::{HistoryInspector, InspectError};
+
+mod promote;
+pub use promote::{PredictionCommandsExt, demote_predicted, promote_predicted};
+
+mod reset;
+pub(crate) use reset::{clear_histories, seed_predicted_histories};
+
+mod remap;
+pub use remap::RemapHistories;
+
+mod snapshot;
+pub use snapshot::WorldSnapshot;
+pub(crate) use snapshot::register_resource as register_snapshot_resource;
+
 #[cfg(test)]
-mod test_utils;
+pub(crate) mod test_utils;
+
+use std::fmt::Debug;
 
-use bevy::{ecs::component::ComponentId, platform::collections::HashMap, prelude::*};
+use bevy::{
+    ecs::{
+        component::ComponentId, entity::MapEntities, reflect::ReflectComponent,
+        relationship::Relationship,
+    },
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    reflect::TypeRegistration,
+};
 use component::HistoryComponent;
+use serde::{Serialize, de::DeserializeOwned};
 
-// TODO: Add some extra safeguards to check types and reduce places to duplicate them
+// Type-erased accesses are validated against the registry in debug builds,
+// see `HistoryComponent::check_history` and `HistoryComponent::check_component`
+// TODO: Reduce places to duplicate type information
 
 pub struct HistoryPlugin;
 
@@ -37,22 +66,51 @@ impl Plugin for HistoryPlugin {
 }
 
 #[allow(unused)]
-pub(crate) use authoritative::{remove_authoritative_history, write_authoritative_history};
+pub(crate) use authoritative::{
+    ConfirmedValue, remove_authoritative_history, remove_confirmed, remove_history_internal,
+    write_authoritative_history, write_confirmed, write_erased_authoritative_history,
+    write_reflected_authoritative_history, write_serialized_authoritative_history,
+};
 
 #[derive(Resource, Default)]
 pub struct RollbackRegistry {
     pub ids: HashMap<ComponentId, usize>,
     pub components: Vec<HistoryComponent>,
+    /// The components that receive authoritative values from the server
+    pub authoritative: HashSet<ComponentId>,
+    /// The confirmed value components of promotable components, kept on entities that
+    /// aren't predicted
+    pub confirmed: HashMap<ComponentId, ComponentId>,
 }
 
 impl RollbackRegistry {
-    pub fn register<T: Component + Clone + PartialEq>(&mut self, world: &mut World) {
+    pub fn register<T: Component + Clone + PartialEq + Debug>(&mut self, world: &mut World) {
         let id = world.register_component::<T>();
         self.ids.insert(id, self.components.len());
         self.components.push(HistoryComponent::new::<T>());
     }
 
-    pub fn register_with_load<T: Component + Clone + PartialEq>(
+    pub fn register_mapped<T: Component + MapEntities + Clone + PartialEq + Debug>(
+        &mut self,
+        world: &mut World,
+    ) {
+        let id = world.register_component::<T>();
+        self.ids.insert(id, self.components.len());
+        self.components
+            .push(HistoryComponent::new::<T>().with_map_entities::<T>());
+    }
+
+    pub fn register_relationship<R: Relationship + Clone + PartialEq + Debug>(
+        &mut self,
+        world: &mut World,
+    ) {
+        let id = world.register_component::<R>();
+        self.ids.insert(id, self.components.len());
+        self.components
+            .push(HistoryComponent::new::<R>().with_component_entities::<R>());
+    }
+
+    pub fn register_with_load<T: Component + Clone + PartialEq + Debug>(
         &mut self,
         world: &mut World,
         load_fn: LoadFn<T>,
@@ -62,4 +120,27 @@ impl RollbackRegistry {
         self.components
             .push(HistoryComponent::with_load::<T>(load_fn));
     }
+
+    pub fn register_serialized<T: Component + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        world: &mut World,
+    ) {
+        let id = world.register_component::<T>();
+        self.ids.insert(id, self.components.len());
+        self.components.push(HistoryComponent::serialized::<T>());
+    }
+
+    pub fn register_reflect(&mut self, world: &mut World, registration: &TypeRegistration) {
+        let Some(reflect_component) = registration.data::<ReflectComponent>() else {
+            panic!(
+                "{} is missing ReflectComponent type data",
+                registration.type_info().type_path(),
+            );
+        };
+        let id = reflect_component.register_component(world);
+        let layout = world.components().get_info(id).unwrap().layout();
+        self.ids.insert(id, self.components.len());
+        self.components
+            .push(HistoryComponent::reflected(registration, layout));
+    }
 }
@@ -2,7 +2,10 @@ use super::{
     RollbackRegistry,
     component_history::{ComponentHistory, EntityHistory, TickData},
 };
-use crate::{RollbackFrames, RollbackSchedule, RollbackStoreSet, StoreFor, StoreScheduleLabel};
+use crate::{
+    RollbackCheckpoints, RollbackFrames, RollbackSchedule, RollbackStoreSet, SkipResimulation,
+    StoreFor, StoreScheduleLabel,
+};
 
 use std::num::NonZero;
 
@@ -40,12 +43,17 @@ pub struct PredictedHistory {
 fn run_store(world: &mut World) {
     // TODO: Check rollback frames, if it changed and went up, grow histories first
 
+    let checkpoints = world
+        .get_resource::<RollbackCheckpoints>()
+        .copied()
+        .unwrap_or_default();
+
     world.resource_scope::<ArchetypeCache, _>(|world, mut cache| {
         world.resource_scope::<RollbackRegistry, _>(|world, registry| {
             update_archetype_cache(world, &mut cache, &registry);
 
             world.resource_scope::<StoreFor, _>(|world, tick| {
-                store_components(world, &cache, &registry, *tick);
+                store_components(world, &cache, &registry, *tick, checkpoints);
             });
         });
     });
@@ -105,6 +113,9 @@ fn update_archetype_cache(
 
         for component_id in archetype.components() {
             if let Some(&index) = registry.ids.get(&component_id) {
+                if let Some(info) = world.components().get_info(component_id) {
+                    registry.components[index].check_component(info);
+                }
                 predicted.push((component_id, index));
             }
         }
@@ -129,7 +140,11 @@ fn store_components(
     cache: &ArchetypeCache,
     registry: &RollbackRegistry,
     tick: StoreFor,
+    checkpoints: RollbackCheckpoints,
 ) {
+    let checkpoint = checkpoints.is_checkpoint(*tick);
+    // Changes between checkpoints aren't seen by change detection, so all values are compared
+    let compare_all = checkpoints.interval() > 1;
     let tick = tick.get();
     let hist_size = NonZero::new(
         world
@@ -142,6 +157,10 @@ fn store_components(
 
     let world = world.as_unsafe_world_cell();
     let archetypes = world.archetypes();
+    // Entities skipped by a partial rollback keep their present state, which we shouldn't store
+    // SAFETY: Only component data is accessed mutably
+    let skipped = unsafe { world.get_resource::<SkipResimulation>() };
+    let is_skipped = |entity: &Entity| skipped.is_some_and(|skipped| skipped.contains(entity));
 
     for &id in cache.no_components.iter() {
         for entity in archetypes
@@ -150,6 +169,7 @@ fn store_components(
             .entities()
             .iter()
             .map(|e| e.id())
+            .filter(|entity| !is_skipped(entity))
         {
             let entity_mut = world.get_entity(entity).unwrap();
             // SAFETY: We don't do structural changes in this system
@@ -177,6 +197,7 @@ fn store_components(
             .entities()
             .iter()
             .map(|e| e.id())
+            .filter(|entity| !is_skipped(entity))
         {
             let entity = world.get_entity(entity).unwrap();
             // SAFETY: We don't do structural changes in this system
@@ -207,20 +228,30 @@ fn store_components(
                 let history = history
                     .entry(component_id)
                     .or_insert_with(|| ComponentHistory::from_component(component, hist_size));
+                component.check_history(history);
+                // Between checkpoints, only the first value of new histories is stored
+                if !checkpoint && !history.is_empty() {
+                    continue;
+                }
+                // Check the change ticks directly, since immutable components can't be fetched
+                // mutably
                 // SAFETY: We don't do structural changes in this system
-                let ptr = unsafe { entity.get_mut_by_id(component_id) }.unwrap();
-                if !ptr.is_changed() {
+                let ticks = unsafe { entity.get_change_ticks_by_id(component_id) }.unwrap();
+                if !compare_all && !ticks.is_changed(world.last_change_tick(), world.change_tick())
+                {
                     continue;
                 }
+                // SAFETY: We don't do structural changes in this system
+                let ptr = unsafe { entity.get_by_id(component_id) }.unwrap();
                 if let TickData::Value(prev_ptr) = history.get_latest(tick.saturating_sub(1)) {
                     // SAFETY: Both the history and component were fetched using the same ComponentId
-                    let equal = unsafe { component.equal(prev_ptr, ptr.as_ref()) };
+                    let equal = unsafe { component.equal(prev_ptr, ptr) };
                     if equal {
                         continue;
                     }
                 }
                 // SAFETY: Both the history and component were fetched using the same ComponentId
-                unsafe { history.write(tick, |dst| component.store(ptr.as_ref(), dst)) };
+                unsafe { history.write(tick, |dst| component.store(ptr, dst)) };
             }
         }
     }
@@ -292,7 +323,7 @@ mod tests {
         super::{component_history::TickData, test_utils::*},
         PredictedHistory, RollbackRegistry,
     };
-    use crate::{Predicted, RollbackFrames};
+    use crate::{Predicted, RollbackCheckpoints, RollbackFrames};
     use TickData::*;
 
     use bevy::prelude::*;
@@ -349,6 +380,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stores_checkpoints() {
+        let mut app = init_app();
+        app.insert_resource(RollbackCheckpoints::new(2));
+
+        let e1 = app
+            .world_mut()
+            .spawn((Predicted, PredictedHistory::default(), A(0)))
+            .id();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(app.world_mut());
+        app.insert_resource(registry);
+
+        let mut e2 = None;
+        for i in 0..=5 {
+            if i == 3 {
+                e2 = Some(
+                    app.world_mut()
+                        .spawn((Predicted, PredictedHistory::default(), A(10)))
+                        .id(),
+                );
+            }
+            app.insert_resource(super::StoreFor(RepliconTick::new(i)));
+            app.update();
+            **app.world_mut().entity_mut(e1).get_mut::<A>().unwrap() += 1;
+            if let Some(e2) = e2 {
+                **app.world_mut().entity_mut(e2).get_mut::<A>().unwrap() += 1;
+            }
+        }
+
+        let world = app.world_mut();
+        let comp_a = world.register_component::<A>();
+        use Missing as M;
+
+        let hist = world.entity(e1).get::<PredictedHistory>().unwrap();
+        for (i, v) in [a(0), M, a(2), M, a(4), M].iter_enumerate() {
+            assert_eq!(v, hist.get(&comp_a).unwrap().get(i as u32).deref().cloned());
+        }
+
+        // New histories store their first value right away
+        let hist = world.entity(e2.unwrap()).get::<PredictedHistory>().unwrap();
+        for (i, v) in [a(10), a(11), M].iter_enumerate() {
+            assert_eq!(
+                v,
+                hist.get(&comp_a)
+                    .unwrap()
+                    .get(i as u32 + 3)
+                    .deref()
+                    .cloned()
+            );
+        }
+    }
+
     #[test]
     fn stores_removed() {
         let mut app = init_app();
@@ -602,6 +687,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stores_immutable_replacements() {
+        let mut app = init_app();
+
+        let e1 = app
+            .world_mut()
+            .spawn((Predicted, PredictedHistory::default(), I(1)))
+            .id();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register::<I>(app.world_mut());
+        app.insert_resource(registry);
+
+        for tick in 0..=3 {
+            if tick == 2 {
+                app.world_mut().entity_mut(e1).insert(I(2));
+            }
+
+            app.insert_resource(super::StoreFor(RepliconTick::new(tick)));
+            app.update();
+        }
+
+        let world = app.world_mut();
+        let comp_i = world.register_component::<I>();
+
+        let e = world.entity(e1);
+        let hist = e.get::<PredictedHistory>().unwrap();
+        for (tick, v) in [
+            (0, Value(I(1))),
+            (1, Missing),
+            (2, Value(I(2))),
+            (3, Missing),
+        ] {
+            assert_eq!(v, hist.get(&comp_i).unwrap().get(tick).deref().cloned());
+        }
+    }
+
     #[test]
     fn drop_once_unique_values() {
         let mut app = init_app();
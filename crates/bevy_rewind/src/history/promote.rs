@@ -0,0 +1,184 @@
+use super::{
+    AuthoritativeHistory, PredictedHistory, RollbackRegistry, component_history::ComponentHistory,
+};
+use crate::{Predicted, RollbackFrames};
+
+use std::num::NonZero;
+
+use bevy::prelude::*;
+use bevy_replicon::client::confirm_history::ConfirmHistory;
+
+/// An extension trait for [`EntityCommands`] to change whether an entity is predicted,
+/// for example based on its distance to the local player
+pub trait PredictionCommandsExt {
+    /// Start predicting the entity, see [`promote_predicted`]
+    fn promote_predicted(&mut self) -> &mut Self;
+    /// Stop predicting the entity, see [`demote_predicted`]
+    fn demote_predicted(&mut self) -> &mut Self;
+}
+
+impl PredictionCommandsExt for EntityCommands<'_> {
+    fn promote_predicted(&mut self) -> &mut Self {
+        self.queue(promote_predicted)
+    }
+
+    fn demote_predicted(&mut self) -> &mut Self {
+        self.queue(demote_predicted)
+    }
+}
+
+/// Make the entity predicted, seeding its histories at the last tick confirmed by the server.
+/// Components registered with [`register_promotable_component`](crate::RollbackApp::register_promotable_component)
+/// are seeded with the last value received from the server, other components with their current
+/// value. This makes sure the first rollback after promotion loads the latest known state,
+/// instead of removing components without history.
+pub fn promote_predicted(mut entity: EntityWorldMut) {
+    if entity.contains::<Predicted>() {
+        return;
+    }
+    let Some(tick) = entity.get::<ConfirmHistory>().map(|c| c.last_tick()) else {
+        // Entities that aren't replicated don't have a state to seed the histories with
+        entity.insert(Predicted);
+        return;
+    };
+
+    let id = entity.id();
+    entity.world_scope(|world| {
+        let hist_size = NonZero::new(
+            world
+                .get_resource::<RollbackFrames>()
+                .copied()
+                .unwrap_or_default()
+                .history_size() as u8,
+        )
+        .unwrap();
+
+        let registry = world.resource::<RollbackRegistry>();
+        let entity = world.entity(id);
+        let mut predicted = PredictedHistory::default();
+        let mut authoritative = AuthoritativeHistory::default();
+        let mut confirmed_ids = Vec::new();
+        for (&comp_id, &index) in registry.ids.iter() {
+            // Values received while the entity wasn't predicted can differ from the current ones,
+            // for example when they're smoothed locally
+            let confirmed = registry.confirmed.get(&comp_id).and_then(|&confirmed_id| {
+                let value = entity.get_by_id(confirmed_id).ok()?;
+                confirmed_ids.push(confirmed_id);
+                Some(value)
+            });
+            // SAFETY: `ConfirmedValue` is transparent, so it points to a value of the component
+            let Some(value) = confirmed.or_else(|| entity.get_by_id(comp_id).ok()) else {
+                continue;
+            };
+            let component = &registry.components[index];
+            let mut history = ComponentHistory::from_component(component, hist_size);
+            // SAFETY: The history and value both belong to the registered component
+            unsafe { history.write(tick.get(), |dst| component.store(value, dst)) };
+
+            if registry.authoritative.contains(&comp_id) {
+                authoritative.insert(comp_id, history);
+            } else {
+                predicted.insert(comp_id, history);
+            }
+        }
+
+        // Confirmed values aren't kept up to date while the entity is predicted
+        world.entity_mut(id).remove_by_ids(&confirmed_ids).insert((
+            Predicted,
+            predicted,
+            authoritative,
+        ));
+    });
+}
+
+/// Stop predicting the entity, freeing its histories
+pub fn demote_predicted(mut entity: EntityWorldMut) {
+    entity.remove::<Predicted>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{ConfirmedValue, component_history::TickData, test_utils::*},
+        *,
+    };
+    use crate::{RollbackApp, tests::init_app};
+
+    #[test]
+    fn promote_and_demote() {
+        let mut world = World::new();
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(&mut world);
+        registry.register::<C>(&mut world);
+        let comp_a = world.register_component::<A>();
+        let comp_c = world.register_component::<C>();
+        registry.authoritative.insert(comp_a);
+        world.insert_resource(registry);
+
+        let entity = world.spawn((A(1), C(2, 3), confirm_history([4, 6]))).id();
+
+        let mut commands = world.commands();
+        commands.entity(entity).promote_predicted();
+        world.flush();
+
+        let entity_ref = world.entity(entity);
+        assert!(entity_ref.contains::<Predicted>());
+        let authoritative = entity_ref.get::<AuthoritativeHistory>().unwrap();
+        assert_eq!(a(1), authoritative[&comp_a].get(6).deref::<A>().cloned());
+        assert!(!authoritative.contains_key(&comp_c));
+        let predicted = entity_ref.get::<PredictedHistory>().unwrap();
+        assert_eq!(
+            TickData::Value(C(2, 3)),
+            predicted[&comp_c].get(6).deref::<C>().cloned()
+        );
+
+        world.commands().entity(entity).demote_predicted();
+        world.flush();
+
+        let entity_ref = world.entity(entity);
+        assert!(!entity_ref.contains::<Predicted>());
+        assert!(!entity_ref.contains::<PredictedHistory>());
+        assert!(!entity_ref.contains::<AuthoritativeHistory>());
+        assert_eq!(Some(&A(1)), entity_ref.get::<A>());
+    }
+
+    #[test]
+    fn promote_seeds_confirmed_values() {
+        let mut world = World::new();
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(&mut world);
+        let comp_a = world.register_component::<A>();
+        let confirmed_a = world.register_component::<ConfirmedValue<A>>();
+        registry.authoritative.insert(comp_a);
+        registry.confirmed.insert(comp_a, confirmed_a);
+        world.insert_resource(registry);
+
+        // The current value was changed locally after the last value received from the server
+        let entity = world
+            .spawn((A(3), ConfirmedValue(A(1)), confirm_history([4, 6])))
+            .id();
+
+        world.commands().entity(entity).promote_predicted();
+        world.flush();
+
+        let entity_ref = world.entity(entity);
+        let authoritative = entity_ref.get::<AuthoritativeHistory>().unwrap();
+        assert_eq!(a(1), authoritative[&comp_a].get(6).deref::<A>().cloned());
+        assert!(!entity_ref.contains::<ConfirmedValue<A>>());
+        assert_eq!(Some(&A(3)), entity_ref.get::<A>());
+    }
+
+    #[test]
+    fn only_keeps_confirmed_values_of_promotable() {
+        let mut app = init_app(PostUpdate, FixedUpdate);
+        app.register_authoritative_component::<A>()
+            .register_authoritative_component::<C>()
+            .register_promotable_component::<C>();
+        let comp_a = app.world_mut().register_component::<A>();
+        let comp_c = app.world_mut().register_component::<C>();
+
+        let registry = app.world().resource::<RollbackRegistry>();
+        assert!(!registry.confirmed.contains_key(&comp_a));
+        assert!(registry.confirmed.contains_key(&comp_c));
+    }
+}
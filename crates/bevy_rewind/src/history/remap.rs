@@ -0,0 +1,138 @@
+use super::{AuthoritativeHistory, PredictedHistory, RollbackRegistry};
+
+use bevy::{
+    ecs::{
+        entity::{EntityHashMap, EntityMapper},
+        entity_disabling::Disabled,
+    },
+    prelude::*,
+};
+
+/// A command mapping the entities held by history values of components registered with
+/// [`MapEntities`](bevy::ecs::entity::MapEntities), for example with
+/// [`register_mapped_predicted_component`](crate::RollbackApp::register_mapped_predicted_component).
+///
+/// Queue it when an entity is replaced by another one, so values stored before the replacement
+/// point to the new entity when they are loaded. Entities missing from the map are kept as is.
+///
+/// Predicted spawns matched to a server entity through a replicon `Signature` keep their client
+/// entity, so they don't need to be remapped. `bevy_rewind_entity_management` keeps predicted
+/// spawns that were rolled back until they fall out of history, so a server entity received late
+/// is still matched to them.
+#[derive(Clone, Debug, Default, Deref, DerefMut)]
+pub struct RemapHistories(pub EntityHashMap<Entity>);
+
+impl RemapHistories {
+    /// Construct a `RemapHistories` replacing a single entity
+    pub fn single(from: Entity, to: Entity) -> Self {
+        Self(EntityHashMap::from_iter([(from, to)]))
+    }
+}
+
+impl Command for RemapHistories {
+    fn apply(mut self, world: &mut World) {
+        if self.is_empty() {
+            return;
+        }
+
+        world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+            if !registry.components.iter().any(|c| c.maps_entities()) {
+                return;
+            }
+
+            let mut query = world.query_filtered::<
+                (&mut PredictedHistory, &mut AuthoritativeHistory),
+                Or<(With<Disabled>, Without<Disabled>)>,
+            >();
+            for (mut predicted, mut authoritative) in query.iter_mut(world) {
+                let histories = predicted
+                    .iter_mut()
+                    .chain(authoritative.iter_mut())
+                    .filter_map(|(id, history)| {
+                        let component = &registry.components[*registry.ids.get(id)?];
+                        component.maps_entities().then_some((component, history))
+                    });
+                for (component, history) in histories {
+                    component.check_history(history);
+                    history.for_each_value_mut(|stored| {
+                        let mapper: &mut dyn EntityMapper = &mut self.0;
+                        // SAFETY: The history was checked to belong to the component
+                        unsafe { component.map_entities(stored, mapper) };
+                    });
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Predicted,
+        history::{component_history::TickData, test_utils::*},
+    };
+
+    use bevy::ecs::entity::MapEntities;
+
+    #[derive(Component, Clone, PartialEq, Debug)]
+    struct Target(Entity);
+
+    impl MapEntities for Target {
+        fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+            self.0 = entity_mapper.get_mapped(self.0);
+        }
+    }
+
+    #[test]
+    fn remaps_histories() {
+        let mut world = World::new();
+        let mut registry = RollbackRegistry::default();
+        registry.register_mapped::<Target>(&mut world);
+        registry.register::<A>(&mut world);
+        world.insert_resource(registry);
+        let comp_target = world.register_component::<Target>();
+
+        let old = world.spawn_empty().id();
+        let new = world.spawn_empty().id();
+        let other = world.spawn_empty().id();
+
+        let mut predicted = PredictedHistory::default();
+        let registry = world.resource::<RollbackRegistry>();
+        let component = &registry.components[registry.ids[&comp_target]];
+        predicted.insert(
+            comp_target,
+            stored_comp_history(
+                component,
+                0,
+                [
+                    TickData::Value(Target(old)),
+                    TickData::Removed,
+                    TickData::Value(Target(other)),
+                ],
+            ),
+        );
+        let mut authoritative = AuthoritativeHistory::default();
+        authoritative.insert(
+            comp_target,
+            stored_comp_history(component, 1, [TickData::Value(Target(old))]),
+        );
+        let entity = world
+            .spawn((Predicted, predicted, authoritative, Disabled))
+            .id();
+
+        RemapHistories::single(old, new).apply(&mut world);
+
+        let entity = world.entity(entity);
+        let predicted = entity.get::<PredictedHistory>().unwrap();
+        let history = &predicted[&comp_target];
+        assert_eq!(TickData::Value(&Target(new)), history.get(0).deref());
+        assert_eq!(TickData::Removed, history.get(1).deref::<Target>());
+        assert_eq!(TickData::Value(&Target(other)), history.get(2).deref());
+        let authoritative = entity.get::<AuthoritativeHistory>().unwrap();
+        assert_eq!(
+            TickData::Value(&Target(new)),
+            authoritative[&comp_target].get(1).deref()
+        );
+    }
+}
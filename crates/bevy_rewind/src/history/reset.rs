@@ -0,0 +1,67 @@
+use super::{
+    AuthoritativeHistory, PredictedHistory, RollbackRegistry,
+    component_history::{ComponentHistory, TickData},
+};
+use crate::{Predicted, RollbackFrames};
+
+use std::num::NonZero;
+
+use bevy::{ecs::entity_disabling::Disabled, prelude::*};
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// Clear the histories of all predicted entities
+pub(crate) fn clear_histories(world: &mut World) {
+    let mut query = world.query_filtered::<
+        (&mut PredictedHistory, &mut AuthoritativeHistory),
+        Or<(With<Disabled>, Without<Disabled>)>,
+    >();
+    for (mut predicted, mut authoritative) in query.iter_mut(world) {
+        *predicted = PredictedHistory::default();
+        authoritative.clear();
+    }
+}
+
+/// Store the current values of predicted-only components on the tick, for components that have
+/// no history on or before it. This makes sure the first rollback of a session loads them,
+/// instead of removing them until the tick they were first stored on.
+pub(crate) fn seed_predicted_histories(world: &mut World, tick: RepliconTick) {
+    let hist_size = NonZero::new(
+        world
+            .get_resource::<RollbackFrames>()
+            .copied()
+            .unwrap_or_default()
+            .history_size() as u8,
+    )
+    .unwrap();
+
+    world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+        let entities = world
+            .query_filtered::<Entity, (With<Predicted>, Or<(With<Disabled>, Without<Disabled>)>)>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for id in entities {
+            let mut predicted =
+                std::mem::take(&mut *world.get_mut::<PredictedHistory>(id).unwrap());
+            let entity = world.entity(id);
+            for (&comp_id, &index) in registry.ids.iter() {
+                if registry.authoritative.contains(&comp_id) {
+                    continue;
+                }
+                let Ok(value) = entity.get_by_id(comp_id) else {
+                    continue;
+                };
+                let component = &registry.components[index];
+                let history = predicted
+                    .entry(comp_id)
+                    .or_insert_with(|| ComponentHistory::from_component(component, hist_size));
+                component.check_history(history);
+                if !matches!(history.get_latest(tick.get()), TickData::Missing) {
+                    continue;
+                }
+                // SAFETY: The history and value both belong to the registered component
+                unsafe { history.write(tick.get(), |dst| component.store(value, dst)) };
+            }
+            *world.get_mut::<PredictedHistory>(id).unwrap() = predicted;
+        }
+    });
+}
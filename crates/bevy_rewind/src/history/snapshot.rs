@@ -0,0 +1,271 @@
+use super::{
+    RemapHistories, RollbackRegistry,
+    batch::{InsertBatch, RemoveBatch},
+    component_history::{ComponentHistory, TickData},
+};
+use crate::Predicted;
+
+use std::{any::Any, num::NonZero};
+
+use bevy::{
+    ecs::{
+        component::ComponentId,
+        entity::{EntityHashMap, EntityHashSet},
+        entity_disabling::Disabled,
+        system::EntityCommand,
+        world::CommandQueue,
+    },
+    prelude::*,
+};
+
+/// An owned snapshot of all registered components on [`Predicted`] entities and all registered
+/// predicted resources. Can be used for checkpoints or instant replays, without going through
+/// the network.
+pub struct WorldSnapshot {
+    entities: Vec<EntitySnapshot>,
+    resources: Vec<ResourceSnapshot>,
+}
+
+struct EntitySnapshot {
+    entity: Entity,
+    // Each history only stores a single value
+    components: Vec<(ComponentId, ComponentHistory)>,
+}
+
+struct ResourceSnapshot {
+    value: Option<Box<dyn Any + Send + Sync>>,
+    restore: fn(&mut World, Option<&(dyn Any + Send + Sync)>),
+}
+
+impl WorldSnapshot {
+    /// Capture the current state of the predicted world
+    pub fn capture(world: &mut World) -> Self {
+        let mut query = world.query_filtered::<EntityRef, (
+            With<Predicted>,
+            Or<(With<Disabled>, Without<Disabled>)>,
+        )>();
+        let world: &World = world;
+        let registry = world.resource::<RollbackRegistry>();
+
+        let entities = query
+            .iter(world)
+            .map(|entity| EntitySnapshot {
+                entity: entity.id(),
+                components: registry
+                    .ids
+                    .iter()
+                    .filter_map(|(&id, &index)| {
+                        let value = entity.get_by_id(id).ok()?;
+                        let component = &registry.components[index];
+                        let mut history =
+                            ComponentHistory::from_component(component, NonZero::<u8>::MIN);
+                        // SAFETY: The history and value both belong to the registered component
+                        unsafe { history.write(0, |dst| component.store(value, dst)) };
+                        Some((id, history))
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let resources = world
+            .get_resource::<ResourceSnapshotRegistry>()
+            .map(|registry| registry.0.iter().map(|capture| capture(world)).collect())
+            .unwrap_or_default();
+
+        Self {
+            entities,
+            resources,
+        }
+    }
+
+    /// Get the entities in the snapshot
+    pub fn entities(&self) -> impl Iterator<Item = Entity> {
+        self.entities.iter().map(|e| e.entity)
+    }
+
+    /// Restore the world to the state of the snapshot.
+    ///
+    /// Predicted entities that weren't part of the snapshot are despawned, entities from the
+    /// snapshot that no longer exist are spawned again. Respawned entities get a new id,
+    /// which replaces the old one in the snapshot and in values of components registered with
+    /// [`MapEntities`](bevy::ecs::entity::MapEntities).
+    pub fn restore(&mut self, world: &mut World) {
+        let snapshot_entities = self.entities().collect::<EntityHashSet>();
+        let despawned = world
+            .query_filtered::<Entity, (With<Predicted>, Or<(With<Disabled>, Without<Disabled>)>)>()
+            .iter(world)
+            .filter(|entity| !snapshot_entities.contains(entity))
+            .collect::<Vec<_>>();
+        for entity in despawned {
+            world.despawn(entity);
+        }
+
+        let mut respawned = EntityHashMap::default();
+        for snapshot in &mut self.entities {
+            if !world.entities().contains(snapshot.entity) {
+                let entity = world.spawn(Predicted).id();
+                respawned.insert(snapshot.entity, entity);
+                snapshot.entity = entity;
+            }
+        }
+
+        world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+            let mut inserts = InsertBatch::new();
+            let mut removes = RemoveBatch::new();
+            let mut load_queue = CommandQueue::default();
+
+            for snapshot in &mut self.entities {
+                let entity = snapshot.entity;
+                if !respawned.is_empty() {
+                    snapshot.remap(&registry, &mut respawned);
+                }
+
+                let mut load_commands =
+                    Commands::new_from_entities(&mut load_queue, world.entities());
+                let entity_ref = world.entity(entity);
+                for (&id, &index) in registry.ids.iter() {
+                    let component = &registry.components[index];
+                    let stored = snapshot
+                        .components
+                        .iter()
+                        .find(|(comp_id, _)| *comp_id == id)
+                        .map(|(_, history)| history.get(0));
+
+                    match stored {
+                        Some(TickData::Value(value)) => {
+                            inserts.try_push(id, component, |dst| unsafe {
+                                component.load_to_uninit(
+                                    None,
+                                    Some(value),
+                                    dst,
+                                    load_commands.reborrow(),
+                                    entity,
+                                )
+                            });
+                        }
+                        _ => {
+                            if entity_ref.contains_id(id) {
+                                removes.push(id);
+                            }
+                        }
+                    }
+                }
+
+                if !inserts.is_empty() {
+                    inserts.clone().apply(world.entity_mut(entity));
+                    inserts.clear();
+                }
+                if !removes.is_empty() {
+                    removes.clone().apply(world.entity_mut(entity));
+                    removes.clear();
+                }
+                load_queue.apply(world);
+            }
+        });
+
+        for resource in &self.resources {
+            (resource.restore)(world, resource.value.as_deref());
+        }
+
+        // Histories from before the snapshot may still point to the despawned entities
+        RemapHistories(respawned).apply(world);
+    }
+}
+
+impl EntitySnapshot {
+    fn remap(&mut self, registry: &RollbackRegistry, map: &mut EntityHashMap<Entity>) {
+        for (id, history) in &mut self.components {
+            let component = &registry.components[registry.ids[id]];
+            if !component.maps_entities() {
+                continue;
+            }
+            history.for_each_value_mut(|stored| {
+                // SAFETY: The history was created for the component
+                unsafe { component.map_entities(stored, map) };
+            });
+        }
+    }
+}
+
+/// Functions to capture the registered predicted resources
+#[derive(Resource, Default)]
+pub(crate) struct ResourceSnapshotRegistry(Vec<fn(&World) -> ResourceSnapshot>);
+
+pub(crate) fn register_resource<T: Resource + Clone>(world: &mut World) {
+    world
+        .get_resource_or_init::<ResourceSnapshotRegistry>()
+        .0
+        .push(capture_resource::<T>);
+}
+
+fn capture_resource<T: Resource + Clone>(world: &World) -> ResourceSnapshot {
+    ResourceSnapshot {
+        value: world
+            .get_resource::<T>()
+            .map(|value| Box::new(value.clone()) as Box<dyn Any + Send + Sync>),
+        restore: restore_resource::<T>,
+    }
+}
+
+fn restore_resource<T: Resource + Clone>(
+    world: &mut World,
+    value: Option<&(dyn Any + Send + Sync)>,
+) {
+    match value.and_then(|value| value.downcast_ref::<T>()) {
+        Some(value) => world.insert_resource(value.clone()),
+        None => {
+            world.remove_resource::<T>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::test_utils::*;
+
+    #[derive(Resource, Clone, PartialEq, Debug)]
+    struct Score(u8);
+
+    #[test]
+    fn capture_and_restore() {
+        let mut world = World::new();
+        let mut registry = RollbackRegistry::default();
+        registry.register::<A>(&mut world);
+        registry.register::<C>(&mut world);
+        world.insert_resource(registry);
+        register_resource::<Score>(&mut world);
+
+        let e1 = world.spawn((Predicted, A(1))).id();
+        let e2 = world.spawn((Predicted, A(2), C(3, 4))).id();
+        let unpredicted = world.spawn(A(5)).id();
+        world.insert_resource(Score(1));
+
+        let mut snapshot = WorldSnapshot::capture(&mut world);
+
+        world.entity_mut(e1).insert((A(10), C(1, 1)));
+        world.despawn(e2);
+        let e3 = world.spawn((Predicted, A(3))).id();
+        world.entity_mut(unpredicted).insert(A(6));
+        world.remove_resource::<Score>();
+
+        snapshot.restore(&mut world);
+
+        assert_eq!(Some(&A(1)), world.get::<A>(e1));
+        assert_eq!(None, world.get::<C>(e1));
+        assert!(world.get_entity(e3).is_err());
+        assert_eq!(Some(&A(6)), world.get::<A>(unpredicted));
+        assert_eq!(Some(&Score(1)), world.get_resource::<Score>());
+
+        let restored = snapshot.entities().find(|&e| e != e1).unwrap();
+        assert_ne!(e2, restored);
+        assert_eq!(Some(&A(2)), world.get::<A>(restored));
+        assert_eq!(Some(&C(3, 4)), world.get::<C>(restored));
+
+        // Restoring again reuses the respawned entity
+        world.entity_mut(restored).insert(A(7));
+        snapshot.restore(&mut world);
+        assert_eq!(Some(&A(2)), world.get::<A>(restored));
+        assert_eq!(2, snapshot.entities().count());
+    }
+}
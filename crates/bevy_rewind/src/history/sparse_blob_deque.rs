@@ -89,6 +89,15 @@ impl SparseBlobDeque {
         self.items.get(item_index as usize)
     }
 
+    /// Call a function on each stored item, from front to back
+    pub fn for_each_mut(&mut self, mut f: impl FnMut(PtrMut)) {
+        for index in 0..self.items.len() {
+            if let Some(ptr) = self.items.get_mut(index) {
+                f(ptr);
+            }
+        }
+    }
+
     pub unsafe fn append<'a>(&mut self, write_fn: Option<impl FnOnce(PtrMut<'a>)>) {
         if self.len == self.capacity {
             let index_bit = 1 << (self.len - 1);
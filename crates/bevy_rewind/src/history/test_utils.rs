@@ -7,6 +7,7 @@ use super::{
 };
 
 use std::{
+    fmt::Debug,
     num::NonZero,
     sync::{Arc, RwLock},
 };
@@ -18,6 +19,7 @@ use bevy::{
     ptr::{Ptr, PtrMut},
 };
 use bevy_replicon::{client::confirm_history::ConfirmHistory, shared::replicon_tick::RepliconTick};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 // Test components
 
@@ -91,6 +93,41 @@ impl Drop for D {
 #[derive(Component, Clone, PartialEq, Deref, DerefMut, Debug)]
 pub struct F(pub f32);
 
+// An immutable component, which can only be replaced
+#[derive(Component, Clone, PartialEq, Eq, Debug)]
+#[component(immutable)]
+pub struct I(pub u16);
+
+pub fn i(v: u16) -> TickData<I> {
+    TickData::Value(I(v))
+}
+
+// A component without Clone, which can only be stored as serialized bytes
+#[derive(Component, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct S(pub Vec<u16>);
+
+// A component whose serialized form can't be deserialized, untagged enums need `deserialize_any`
+#[derive(Component, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum U {
+    Number(u16),
+    Text(String),
+}
+
+// A component without Clone, which can only be registered through reflection
+#[derive(Component, Reflect, PartialEq, Eq, Debug)]
+#[reflect(Component, PartialEq)]
+pub struct R(pub u16);
+
+// A component whose values can't be compared through reflection, as its field is opaque
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+pub struct NoEq(pub Opaque);
+
+#[derive(Reflect, Clone, Default)]
+#[reflect(opaque)]
+pub struct Opaque;
+
 // Helpers
 
 pub fn r_tick(tick: u32) -> RepliconTick {
@@ -147,7 +184,7 @@ impl<V, I: IntoIterator<Item = V>> IterEnumerate for I {
 
 // Shorthand constructors
 
-pub fn comp_history<T: Component + Clone + PartialEq>(
+pub fn comp_history<T: Component + Clone + PartialEq + Debug>(
     first_tick: u32,
     data: impl IntoIterator<Item = TickData<T>>,
 ) -> ComponentHistory {
@@ -174,7 +211,43 @@ pub fn comp_history<T: Component + Clone + PartialEq>(
     comp_hist
 }
 
-pub fn pred_history<T: Component + Clone + PartialEq>(
+pub fn serialized_comp_history<T: Component + Serialize + DeserializeOwned + Debug>(
+    first_tick: u32,
+    data: impl IntoIterator<Item = TickData<T>>,
+) -> ComponentHistory {
+    stored_comp_history(&HistoryComponent::serialized::<T>(), first_tick, data)
+}
+
+// Construct a history for a component that doesn't store plain values
+pub fn stored_comp_history<T>(
+    component: &HistoryComponent,
+    first_tick: u32,
+    data: impl IntoIterator<Item = TickData<T>>,
+) -> ComponentHistory {
+    let data = data.into_iter();
+    let len = data.size_hint().0;
+    let mut comp_hist = ComponentHistory::from_component(component, unsafe {
+        NonZero::new_unchecked(len.max(5) as u8)
+    });
+
+    for (offset, v) in data.enumerate() {
+        let tick = first_tick + offset as u32;
+        match v {
+            TickData::Value(v) => {
+                unsafe { comp_hist.write(tick, |ptr| component.store(Ptr::from(&v), ptr)) };
+            }
+            TickData::Removed => {
+                comp_hist.mark_removed(tick);
+            }
+            TickData::Missing => {
+                todo!();
+            }
+        }
+    }
+    comp_hist
+}
+
+pub fn pred_history<T: Component + Clone + PartialEq + Debug>(
     first_tick: u32,
     comp_id: ComponentId,
     data: impl IntoIterator<Item = TickData<T>>,
@@ -184,7 +257,7 @@ pub fn pred_history<T: Component + Clone + PartialEq>(
     pred_hist
 }
 
-pub fn auth_history<T: Component + Clone + PartialEq>(
+pub fn auth_history<T: Component + Clone + PartialEq + Debug>(
     first_tick: u32,
     comp_id: ComponentId,
     data: impl IntoIterator<Item = TickData<T>>,
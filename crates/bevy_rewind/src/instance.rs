@@ -0,0 +1,264 @@
+use crate::{
+    Predicted, RollbackCheckpoints, RollbackFrames, RollbackSchedule, RollbackStoreSet,
+    RollbackTarget, SessionStart, SkipResimulation, TickSource, calculate_rollback_target,
+    clamp_rollback_target, partial::restore_skipped, trigger_rollback,
+};
+
+use std::marker::PhantomData;
+
+use bevy::{
+    app::{RunFixedMainLoop, RunFixedMainLoopSystem},
+    ecs::entity_disabling::Disabled,
+    prelude::*,
+};
+use bevy_replicon::{
+    client::confirm_history::EntityReplicated, shared::replicon_tick::RepliconTick,
+};
+
+/// A plugin rolling back simulation instances separately, for example matches running in the same
+/// world on a server.
+///
+/// Each [`RollbackInstance`] keeps its own rollback target, corrections for its members only roll
+/// back that instance. While an instance is rolled back, all predicted entities outside of it are
+/// listed in [`SkipResimulation`], so the other instances keep their current state. Simulation
+/// systems should skip them with [`Resimulated`](crate::Resimulated). Instances share the tick,
+/// the [`RollbackFrames`] and the simulation schedule. Predicted resources are rolled back with
+/// every instance, so state of a single instance should live on entities. A
+/// [`MutateTickReceived`](bevy_replicon::client::server_mutate_ticks::MutateTickReceived) or a
+/// manually set [`RollbackTarget`] still rolls back the whole world.
+///
+/// Rolling back an instance is a filter on a regular rollback, not a separate simulation. Each
+/// instance that needs it triggers its own rollback, which runs the whole simulation schedule for
+/// every resimulated tick, with the other instances skipped. Instances rolled back on the same
+/// frame are resimulated one after the other, so the cost grows with the number of instances
+/// rolled back, not with their size.
+///
+/// Instances are only rolled back automatically for corrections received from the server, which
+/// happens on clients. Servers, or anything else that needs an instance to be resimulated, request
+/// it with [`RollbackInstance::request_rollback`].
+///
+/// Instances that need their own tick or rollback settings can run in separate worlds instead,
+/// each with its own [`RollbackPlugin`](crate::RollbackPlugin).
+pub struct RollbackInstancePlugin<Tick: TickSource>(PhantomData<Tick>);
+
+impl<Tick: TickSource> Default for RollbackInstancePlugin<Tick> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Tick: TickSource> Plugin for RollbackInstancePlugin<Tick> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackInstances>()
+            .add_systems(
+                RunFixedMainLoop,
+                (
+                    collect_instance_targets.before(calculate_rollback_target::<Tick>),
+                    trigger_instance_rollbacks::<Tick>
+                        .after(trigger_rollback::<Tick>)
+                        .before(RunFixedMainLoopSystem::FixedMainLoop),
+                ),
+            )
+            .add_systems(
+                RollbackSchedule::PreRollback,
+                skip_other_instances
+                    .after(RollbackStoreSet)
+                    .run_if(resource_exists::<ResimulatingInstance>),
+            )
+            .add_systems(
+                RollbackSchedule::BackToPresent,
+                restore_skipped.run_if(resource_exists::<ResimulatingInstance>),
+            );
+    }
+}
+
+/// The root of a simulation instance, like a match.
+/// Entities are added to the instance with [`InRollbackInstance`].
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct RollbackInstance {
+    target: Option<RepliconTick>,
+}
+
+impl RollbackInstance {
+    /// Request a rollback of the instance, resimulating from the tick.
+    /// The earliest requested tick is used, like for the [`RollbackTarget`].
+    pub fn request_rollback(&mut self, tick: impl Into<RepliconTick>) {
+        let tick = tick.into();
+        self.target = Some(self.target.map_or(tick, |target| target.min(tick)));
+    }
+
+    /// The tick the instance will be rolled back to, if a rollback was requested
+    pub fn target(&self) -> Option<RepliconTick> {
+        self.target
+    }
+}
+
+/// A component adding an entity to the [`RollbackInstance`] on the root entity.
+/// Corrections received for the entity only roll back its instance.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Deref, Debug)]
+pub struct InRollbackInstance(pub Entity);
+
+/// A marker resource for apps rolling back instances separately
+#[derive(Resource, Default)]
+pub(crate) struct RollbackInstances;
+
+/// A resource holding the root of the instance being rolled back, only present while
+/// the [`RollbackInstancePlugin`] rolls back an instance
+#[derive(Resource, Clone, Copy, Deref, Debug)]
+pub struct ResimulatingInstance(Entity);
+
+fn collect_instance_targets(
+    mut replicated: EventReader<EntityReplicated>,
+    members: Query<&InRollbackInstance, Or<(With<Disabled>, Without<Disabled>)>>,
+    mut instances: Query<&mut RollbackInstance>,
+) {
+    for event in replicated.read() {
+        let root = members
+            .get(event.entity)
+            .map_or(event.entity, |member| **member);
+        if let Ok(mut instance) = instances.get_mut(root) {
+            instance.request_rollback(event.tick);
+        }
+    }
+}
+
+/// Roll back each instance with a target, resimulating the whole schedule once per instance
+fn trigger_instance_rollbacks<Tick: TickSource>(world: &mut World) {
+    let tick: RepliconTick = (*world.resource::<Tick>()).into();
+    let frames = *world.resource::<RollbackFrames>();
+    let checkpoints = *world.resource::<RollbackCheckpoints>();
+    let session_start = world.get_resource::<SessionStart>().map(|start| **start);
+
+    let mut targets = world
+        .query::<(Entity, &mut RollbackInstance)>()
+        .iter_mut(world)
+        .filter_map(|(root, mut instance)| Some((root, instance.target.take()?)))
+        .collect::<Vec<_>>();
+    // Roll back instances in a consistent order
+    targets.sort_by_key(|&(root, _)| root);
+
+    for (root, target) in targets {
+        let target = clamp_rollback_target(target, tick, &frames, &checkpoints, session_start);
+        if target >= tick {
+            continue;
+        }
+
+        world.insert_resource(ResimulatingInstance(root));
+        **world.resource_mut::<RollbackTarget>() = Some(target);
+        trigger_rollback::<Tick>(world);
+        world.remove_resource::<ResimulatingInstance>();
+    }
+}
+
+fn skip_other_instances(
+    mut commands: Commands,
+    instance: Res<ResimulatingInstance>,
+    q: Query<
+        (Entity, Option<&InRollbackInstance>),
+        (
+            Or<(With<Predicted>, With<RollbackInstance>)>,
+            Or<(With<Disabled>, Without<Disabled>)>,
+        ),
+    >,
+) {
+    let skipped = q
+        .iter()
+        .filter(|&(entity, member)| {
+            entity != **instance && member.is_none_or(|member| **member != **instance)
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+    commands.insert_resource(SkipResimulation(skipped));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Tick, init_app};
+
+    use bevy::ecs::schedule::ScheduleLabel;
+
+    #[derive(Component, Default)]
+    struct Simulated(u32);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    fn instance_app(instances: bool) -> App {
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_systems(
+            FixedUpdate,
+            |mut q: Query<(Entity, &mut Simulated)>, resimulated: crate::Resimulated| {
+                for (entity, mut simulated) in q.iter_mut() {
+                    if resimulated.contains(entity) {
+                        simulated.0 += 1;
+                    }
+                }
+            },
+        );
+        if instances {
+            app.add_plugins(RollbackInstancePlugin::<Tick>::default());
+        }
+        app
+    }
+
+    #[test]
+    fn only_resimulates_instance() {
+        let mut app = instance_app(true);
+        let world = app.world_mut();
+        let match_a = world.spawn(RollbackInstance::default()).id();
+        let match_b = world.spawn(RollbackInstance::default()).id();
+        let corrected = world
+            .spawn((Predicted, Simulated::default(), InRollbackInstance(match_a)))
+            .id();
+        let same_match = world
+            .spawn((Predicted, Simulated::default(), InRollbackInstance(match_a)))
+            .id();
+        let other_match = world
+            .spawn((Predicted, Simulated::default(), InRollbackInstance(match_b)))
+            .id();
+
+        world.send_event(EntityReplicated {
+            entity: corrected,
+            tick: RepliconTick::new(14),
+        });
+        app.update();
+
+        // Ticks 14 and 15 are resimulated for the corrected match, followed by the regular tick
+        let world = app.world();
+        assert_eq!(3, world.get::<Simulated>(corrected).unwrap().0);
+        assert_eq!(3, world.get::<Simulated>(same_match).unwrap().0);
+        assert_eq!(1, world.get::<Simulated>(other_match).unwrap().0);
+        assert!(!world.contains_resource::<SkipResimulation>());
+        assert!(
+            world
+                .get::<RollbackInstance>(match_a)
+                .unwrap()
+                .target()
+                .is_none()
+        );
+        assert!(world.resource::<RollbackTarget>().is_none());
+    }
+
+    #[test]
+    fn resimulates_world_without_plugin() {
+        let mut app = instance_app(false);
+        let world = app.world_mut();
+        let match_a = world.spawn(RollbackInstance::default()).id();
+        let corrected = world
+            .spawn((Predicted, Simulated::default(), InRollbackInstance(match_a)))
+            .id();
+        let other = world.spawn((Predicted, Simulated::default())).id();
+
+        world.send_event(EntityReplicated {
+            entity: corrected,
+            tick: RepliconTick::new(14),
+        });
+        app.update();
+
+        // Without the plugin, corrections of members roll back the whole world
+        let world = app.world();
+        assert_eq!(3, world.get::<Simulated>(corrected).unwrap().0);
+        assert_eq!(3, world.get::<Simulated>(other).unwrap().0);
+    }
+}
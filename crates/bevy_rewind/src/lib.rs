@@ -1,35 +1,93 @@
 //! A crate for generic rollback handling in bevy
 
+// Allow the derive macros to refer to this crate from within it
+extern crate self as bevy_rewind;
+
 mod history;
-pub use history::{AuthoritativeHistory, ExistingOrUninit};
+pub use history::{
+    AuthoritativeHistory, ExistingOrUninit, HistoryInspector, InspectError, PredictionCommandsExt,
+    RemapHistories, WorldSnapshot, demote_predicted, promote_predicted,
+};
 use history::{LoadFn, RollbackRegistry};
 
 mod predicted_resource;
 pub use predicted_resource::ResourceHistory;
 
+mod predicted_event;
+pub use predicted_event::{PredictedEvent, PredictedEventReader};
+
+mod predicted_state;
+
+mod registration;
+pub use bevy_rewind_macros::Rollback;
+#[doc(hidden)]
+pub use registration::macro_exports as __macro_exports;
+pub use registration::{ReflectRollbackComponent, RollbackComponent, RollbackRegistrationPlugin};
+
+#[cfg(feature = "dump")]
+mod dump;
+#[cfg(feature = "dump")]
+pub use dump::{DumpError, DumpRollbackHistory, EntityDump, HistoryDump, RollbackDump};
+
+mod recording;
+pub use recording::{
+    RecordedFrame, RecordedValue, RecordingError, SaveSessionRecording, SessionRecordSet,
+    SessionRecorder, SessionRecorderPlugin, SessionRecording, SessionReplay, SessionReplayPlugin,
+};
+
+mod effects;
+pub use effects::{EffectCancelled, EffectPlugin, EffectReason, EffectTriggered, Effects};
+
+mod partial;
+pub use partial::{PartialRollbackPlugin, Resimulated, RollbackGroup, SkipResimulation};
+
+mod rng;
+pub use rng::{RngStream, RollbackRng, RollbackRngPlugin, RollbackRngSeed};
+
+mod timer;
+pub use timer::{RollbackTimer, RollbackTimerPlugin};
+
+mod session;
+pub use session::{AwaitingSnapshot, ResetRollback, SessionStart};
+
+mod instance;
+use instance::RollbackInstances;
+pub use instance::{
+    InRollbackInstance, ResimulatingInstance, RollbackInstance, RollbackInstancePlugin,
+};
+
+mod hierarchy;
+pub use hierarchy::{PredictHierarchy, PredictedHierarchyPlugin};
+
 mod load;
 use load::{load_and_clear_resource_prediction, reinsert_predicted_resource};
 
-use std::{fmt::Debug, marker::PhantomData};
+use std::{fmt::Debug, marker::PhantomData, time::Duration};
 
 use bevy::{
     app::RunFixedMainLoop,
     ecs::{
-        component::{HookContext, Mutable},
-        intern::Interned,
-        schedule::ScheduleLabel,
-        world::DeferredWorld,
+        component::HookContext, entity::MapEntities, entity_disabling::Disabled, intern::Interned,
+        relationship::Relationship, schedule::ScheduleLabel, world::DeferredWorld,
     },
+    platform::time::Instant,
     prelude::*,
+    state::state::{
+        FreelyMutableState, StateTransition, StateTransitionEvent, StateTransitionSteps,
+    },
 };
 use bevy_replicon::{
     client::{confirm_history::EntityReplicated, server_mutate_ticks::MutateTickReceived},
     prelude::*,
     shared::{
-        replication::{command_markers::MarkerConfig, track_mutate_messages::TrackAppExt},
+        replication::{
+            command_markers::MarkerConfig, registry::command_fns::MutWrite,
+            track_mutate_messages::TrackAppExt,
+        },
         replicon_tick::RepliconTick,
     },
 };
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 /// The source of the current simulation tick
 pub trait TickSource: Resource + Copy + From<RepliconTick> + Into<RepliconTick> {}
@@ -78,6 +136,7 @@ impl<Tick: TickSource> Plugin for RollbackPlugin<Tick> {
         .init_schedule(RollbackSchedule::PreResimulation)
         .init_schedule(RollbackSchedule::PostResimulation)
         .init_schedule(RollbackSchedule::BackToPresent)
+        .init_schedule(RollbackSchedule::Reset)
         // Since all our schedules probably won't run many systems
         // the single threaded executor should be faster
         .edit_schedule(RollbackSchedule::PreRollback, make_single_threaded)
@@ -86,6 +145,7 @@ impl<Tick: TickSource> Plugin for RollbackPlugin<Tick> {
         .edit_schedule(RollbackSchedule::PreResimulation, make_single_threaded)
         .edit_schedule(RollbackSchedule::PostResimulation, make_single_threaded)
         .edit_schedule(RollbackSchedule::BackToPresent, make_single_threaded)
+        .edit_schedule(RollbackSchedule::Reset, make_single_threaded)
         // Configure run condition for PreResimulation on the first frame
         .configure_sets(
             RollbackSchedule::PreResimulation,
@@ -94,13 +154,19 @@ impl<Tick: TickSource> Plugin for RollbackPlugin<Tick> {
         // Init resources
         .init_resource::<RollbackRegistry>()
         .init_resource::<RollbackFrames>()
+        .init_resource::<RollbackCheckpoints>()
+        .init_resource::<ResimulationBudget>()
         .init_resource::<RollbackTarget>()
         .init_resource::<RequestedRollback>()
+        .add_event::<ResimulationBudgetExceeded>()
         // Store configured schedules
         .insert_resource(StoreScheduleLabel(self.store_schedule))
         .insert_resource(SimulationScheduleLabel(self.rollback_schedule))
         // Set up the history plugin
         .add_plugins(history::HistoryPlugin)
+        // Reset histories between sessions
+        .add_systems(OnEnter(ClientState::Disconnected), session::reset_rollback)
+        .add_systems(OnEnter(ClientState::Connected), session::await_snapshot)
         // Set up resimulate systems
         .add_systems(
             self.store_schedule,
@@ -113,7 +179,13 @@ impl<Tick: TickSource> Plugin for RollbackPlugin<Tick> {
         .add_systems(
             RunFixedMainLoop,
             (
-                calculate_rollback_target::<Tick>,
+                validate_checkpoints.run_if(
+                    resource_changed::<RollbackCheckpoints>.or(resource_changed::<RollbackFrames>),
+                ),
+                session::start_session.run_if(resource_exists::<AwaitingSnapshot>),
+                calculate_rollback_target::<Tick>.run_if(not(resource_exists::<AwaitingSnapshot>)),
+                catch_up::<Tick>
+                    .run_if(resource_exists::<PendingCatchUp>.and(not(rollback_requested))),
                 trigger_rollback::<Tick>.run_if(rollback_requested),
             )
                 .chain()
@@ -149,13 +221,25 @@ fn calculate_rollback_target<Tick: TickSource>(
     mut global_confirms: EventReader<MutateTickReceived>,
     tick: Res<Tick>,
     frames: ResMut<RollbackFrames>,
+    checkpoints: Res<RollbackCheckpoints>,
     mut rollback_target: ResMut<RollbackTarget>,
     mut requested_info: ResMut<RequestedRollback>,
+    session_start: Option<Res<SessionStart>>,
+    instances: Option<Res<RollbackInstances>>,
+    scoped: Query<
+        (),
+        (
+            Or<(With<InRollbackInstance>, With<RollbackInstance>)>,
+            Or<(With<Disabled>, Without<Disabled>)>,
+        ),
+    >,
 ) {
     let tick = (*tick).into();
 
     for event_tick in individual_confirms
         .read()
+        // Entities in an instance only roll back their instance, when instances are rolled back
+        .filter(|c| instances.is_none() || !scoped.contains(c.entity))
         .map(|c| c.tick)
         .chain(global_confirms.read().map(|c| c.tick))
     {
@@ -164,8 +248,13 @@ fn calculate_rollback_target<Tick: TickSource>(
             .or(Some(event_tick))
     }
 
-    let min = tick.get().saturating_sub(frames.max_frames() as u32 - 2);
-    let target = RepliconTick::new(rollback_target.unwrap_or(tick).get().max(min));
+    let target = clamp_rollback_target(
+        rollback_target.unwrap_or(tick),
+        tick,
+        &frames,
+        &checkpoints,
+        session_start.map(|start| **start),
+    );
 
     **requested_info = (tick.get() as i64 - target.get() as i64) as i16;
     // Trigger a rollback, but only if the target is in the past
@@ -175,6 +264,44 @@ fn calculate_rollback_target<Tick: TickSource>(
     **rollback_target = Some(target);
 }
 
+/// Clamp a rollback target to the ticks that can still be loaded from history
+pub(crate) fn clamp_rollback_target(
+    target: RepliconTick,
+    tick: RepliconTick,
+    frames: &RollbackFrames,
+    checkpoints: &RollbackCheckpoints,
+    session_start: Option<RepliconTick>,
+) -> RepliconTick {
+    let min = tick.get().saturating_sub(frames.max_frames() as u32 - 2);
+    // The checkpoint before a target is only in the history if the target is far enough ahead
+    let min = (min + checkpoints.interval() - 1).min(tick.get());
+    // Ticks from before the session have no valid history
+    let session_start = session_start.map_or(0, |start| start.get()).min(tick.get());
+    let min = min.max(session_start);
+    let target = RepliconTick::new(target.get().max(min));
+    if target.get() == 0 || target >= tick {
+        return target;
+    }
+
+    // Start from the tick after the checkpoint, the tick before the session start is always stored
+    let load = checkpoints.checkpoint_before(RepliconTick::new(target.get() - 1));
+    let load = load.get().max(session_start.saturating_sub(1));
+    RepliconTick::new(load + 1)
+}
+
+/// Cap the checkpoint interval, so there is always a checkpoint in the history to roll back to
+fn validate_checkpoints(frames: Res<RollbackFrames>, mut checkpoints: ResMut<RollbackCheckpoints>) {
+    let max_interval = (frames.max_frames() as u32).saturating_sub(2).max(1);
+    if checkpoints.interval() > max_interval {
+        warn!(
+            "Rollback checkpoint interval {} exceeds the {} ticks allowed by the rollback frames",
+            checkpoints.interval(),
+            max_interval,
+        );
+        *checkpoints = RollbackCheckpoints::new(max_interval);
+    }
+}
+
 #[derive(Resource, Deref)]
 struct SimulationScheduleLabel(Interned<dyn ScheduleLabel>);
 
@@ -182,9 +309,36 @@ struct SimulationScheduleLabel(Interned<dyn ScheduleLabel>);
 #[derive(Resource)]
 pub struct AlreadyLoaded;
 
-fn trigger_rollback<Tick: TickSource>(world: &mut World) {
+/// A resource only present while components are inserted or removed to load history.
+/// Component hooks and observers can check for it to skip gameplay logic,
+/// like spawning an explosion when a component is added back by a rollback.
+#[derive(Resource)]
+pub struct RollbackLoading;
+
+/// A resource describing the rollback in progress, only present from
+/// [`RollbackSchedule::PreRollback`] up to and including [`RollbackSchedule::BackToPresent`]
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RollbackContext {
+    /// The first resimulated tick
+    pub start: RepliconTick,
+    /// The tick the world returns to after resimulating
+    pub real_tick: RepliconTick,
+    /// The tick currently being resimulated
+    pub current: RepliconTick,
+}
+
+/// A run condition that is true while rolling back and resimulating
+pub fn is_resimulating(context: Option<Res<RollbackContext>>) -> bool {
+    context.is_some()
+}
+
+/// A run condition that is true on the first resimulated tick of a rollback
+pub fn is_first_resimulated_tick(context: Option<Res<RollbackContext>>) -> bool {
+    context.is_some_and(|context| context.current == context.start)
+}
+
+pub(crate) fn trigger_rollback<Tick: TickSource>(world: &mut World) {
     let target = std::mem::take(&mut **world.resource_mut::<RollbackTarget>());
-    let schedule = **world.resource::<SimulationScheduleLabel>();
 
     // Swap to Time<Fixed>
     *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
@@ -192,6 +346,17 @@ fn trigger_rollback<Tick: TickSource>(world: &mut World) {
     let real_tick: RepliconTick = (*world.resource::<Tick>()).into();
     let start = target.unwrap();
 
+    // Ticks that couldn't be resimulated before are caught up as part of the rollback
+    let end = match world.remove_resource::<PendingCatchUp>() {
+        Some(PendingCatchUp(pending)) => RepliconTick::new(real_tick.get() + pending),
+        None => real_tick,
+    };
+    world.insert_resource(RollbackContext {
+        start,
+        real_tick: end,
+        current: start,
+    });
+
     world.run_schedule(RollbackSchedule::PreRollback);
 
     world.insert_resource(LoadFrom(RepliconTick::new(start.get().saturating_sub(1))));
@@ -209,11 +374,74 @@ fn trigger_rollback<Tick: TickSource>(world: &mut World) {
     // The first resimulated frame should be marked as already loaded
     world.insert_resource(AlreadyLoaded);
 
-    for tick in start.get()..=real_tick.get() {
+    resimulate::<Tick>(world, start, end);
+
+    world.run_schedule(RollbackSchedule::BackToPresent);
+    world.remove_resource::<RollbackContext>();
+
+    // Swap back to Time<Virtual>
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
+
+fn catch_up<Tick: TickSource>(world: &mut World) {
+    let Some(PendingCatchUp(pending)) = world.remove_resource::<PendingCatchUp>() else {
+        return;
+    };
+
+    // Swap to Time<Fixed>
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+
+    // The caught up ticks continue from the last resimulated tick, so they're not part of a
+    // rollback and don't get a `RollbackContext`
+    let tick: RepliconTick = (*world.resource::<Tick>()).into();
+    resimulate::<Tick>(
+        world,
+        RepliconTick::new(tick.get() + 1),
+        RepliconTick::new(tick.get() + pending),
+    );
+
+    // Swap back to Time<Virtual>
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
+
+/// Resimulate the ticks from `start` up to and including `end`,
+/// stopping early if the [`ResimulationBudget`] is exceeded
+fn resimulate<Tick: TickSource>(world: &mut World, start: RepliconTick, end: RepliconTick) {
+    let schedule = **world.resource::<SimulationScheduleLabel>();
+    let budget = world
+        .get_resource::<ResimulationBudget>()
+        .copied()
+        .unwrap_or_default();
+    let started = Instant::now();
+
+    for tick in start.get()..=end.get() {
+        let resimulated = tick - start.get();
+        if budget.is_exceeded(resimulated, started.elapsed()) {
+            let skipped = end.get() - tick + 1;
+            match budget.fallback {
+                BudgetFallback::FastForward => {
+                    world.insert_resource(Tick::from(end));
+                }
+                BudgetFallback::CatchUp => {
+                    world.insert_resource(Tick::from(RepliconTick::new(tick - 1)));
+                    world.insert_resource(PendingCatchUp(skipped));
+                }
+            }
+            world.send_event(ResimulationBudgetExceeded {
+                resimulated,
+                skipped,
+                fallback: budget.fallback,
+            });
+            break;
+        }
+
         // Set the correct ticks
         let tick = RepliconTick::new(tick);
         world.insert_resource(LoadFrom(RepliconTick::new(tick.get().saturating_sub(1))));
         world.insert_resource(Tick::from(tick));
+        if let Some(mut context) = world.get_resource_mut::<RollbackContext>() {
+            context.current = tick;
+        }
 
         // Run PreResimulation
         world.run_schedule(RollbackSchedule::PreResimulation);
@@ -226,10 +454,7 @@ fn trigger_rollback<Tick: TickSource>(world: &mut World) {
         world.run_schedule(RollbackSchedule::PostResimulation);
     }
 
-    world.run_schedule(RollbackSchedule::BackToPresent);
-
-    // Swap back to Time<Virtual>
-    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+    world.remove_resource::<AlreadyLoaded>();
 }
 
 #[cfg(test)]
@@ -237,8 +462,9 @@ mod tests {
     use std::time::Duration;
 
     use bevy::{
-        ecs::schedule::InternedScheduleLabel,
+        ecs::{schedule::InternedScheduleLabel, system::RunSystemOnce},
         prelude::*,
+        state::app::StatesPlugin,
         time::{TimePlugin, TimeUpdateStrategy},
     };
     use bevy_replicon::client::server_mutate_ticks::{MutateTickReceived, ServerMutateTicks};
@@ -269,13 +495,19 @@ mod tests {
     #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
     struct NoTy;
 
-    fn init_app() -> App {
+    /// Build an app storing and rolling back the provided schedules at tick 15, recording the
+    /// ticks and deltas of fixed updates. The first update has already run.
+    pub(crate) fn init_app(
+        store_schedule: impl ScheduleLabel,
+        rollback_schedule: impl ScheduleLabel,
+    ) -> App {
         let mut app = App::new();
         app.add_plugins((
+            StatesPlugin,
             RepliconSharedPlugin::default(),
             RollbackPlugin::<Tick> {
-                store_schedule: NoTy.intern(),
-                rollback_schedule: FixedUpdate.intern(),
+                store_schedule: store_schedule.intern(),
+                rollback_schedule: rollback_schedule.intern(),
                 phantom: PhantomData,
             },
             TimePlugin,
@@ -308,7 +540,7 @@ mod tests {
 
     #[test]
     fn rollback_order() {
-        let mut app = init_app();
+        let mut app = init_app(NoTy, FixedUpdate);
         assert_eq!(*app.world().resource::<Tick>(), Tick(15));
 
         #[derive(Resource, Deref, DerefMut, Default)]
@@ -367,9 +599,190 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rollback_to_checkpoint() {
+        let mut world = World::new();
+        world.insert_resource(Tick(20));
+        world.insert_resource(RollbackFrames::new(10));
+        world.insert_resource(RollbackCheckpoints::new(4));
+        world.init_resource::<RequestedRollback>();
+        world.init_resource::<Events<EntityReplicated>>();
+        world.init_resource::<Events<MutateTickReceived>>();
+
+        // Loads from the checkpoint at tick 12
+        world.insert_resource(RollbackTarget(Some(RepliconTick::new(15))));
+        world
+            .run_system_once(calculate_rollback_target::<Tick>)
+            .unwrap();
+        assert_eq!(
+            Some(RepliconTick::new(13)),
+            **world.resource::<RollbackTarget>()
+        );
+        assert_eq!(7, **world.resource::<RequestedRollback>());
+
+        // The checkpoint at tick 8 is no longer in the history
+        world.insert_resource(RollbackTarget(Some(RepliconTick::new(12))));
+        world
+            .run_system_once(calculate_rollback_target::<Tick>)
+            .unwrap();
+        assert_eq!(
+            Some(RepliconTick::new(13)),
+            **world.resource::<RollbackTarget>()
+        );
+
+        // Targets within the history never start after the target
+        for target in 15..20 {
+            world.insert_resource(RollbackTarget(Some(RepliconTick::new(target))));
+            world
+                .run_system_once(calculate_rollback_target::<Tick>)
+                .unwrap();
+            let start = world.resource::<RollbackTarget>().unwrap();
+            assert!(start.get() <= target);
+            // Loads from a checkpoint
+            assert_eq!(0, (start.get() - 1) % 4);
+        }
+
+        // Intervals that don't fit in the history are capped
+        world.insert_resource(RollbackCheckpoints::new(20));
+        world.run_system_once(validate_checkpoints).unwrap();
+        assert_eq!(8, world.resource::<RollbackCheckpoints>().interval());
+        world.insert_resource(RollbackTarget(Some(RepliconTick::new(19))));
+        world
+            .run_system_once(calculate_rollback_target::<Tick>)
+            .unwrap();
+        assert_eq!(
+            Some(RepliconTick::new(17)),
+            **world.resource::<RollbackTarget>()
+        );
+    }
+
+    #[test]
+    fn rollback_context() {
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct Resimulated(Vec<(Tick, bool)>);
+
+        let mut app = init_app(NoTy, FixedUpdate);
+        app.init_resource::<Resimulated>().add_systems(
+            FixedUpdate,
+            (
+                (|mut resimulated: ResMut<Resimulated>,
+                  tick: Res<Tick>,
+                  context: Res<RollbackContext>| {
+                    assert_eq!(context.current, (*tick).into());
+                    assert_eq!(context.start, Tick(13).into());
+                    assert_eq!(context.real_tick, Tick(15).into());
+                    resimulated.push((*tick, false));
+                })
+                .run_if(is_resimulating),
+                (|mut resimulated: ResMut<Resimulated>, tick: Res<Tick>| {
+                    resimulated.push((*tick, true));
+                })
+                .run_if(is_first_resimulated_tick),
+            )
+                .chain(),
+        );
+        app.update();
+        assert!(app.world().resource::<Resimulated>().is_empty());
+
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(Tick(13).into());
+        app.update();
+
+        assert_eq!(
+            **app.world().resource::<Resimulated>(),
+            [
+                (Tick(13), false),
+                (Tick(13), true),
+                (Tick(14), false),
+                (Tick(15), false)
+            ]
+        );
+        assert!(!app.world().contains_resource::<RollbackContext>());
+    }
+
+    #[test]
+    fn budget_fast_forward() {
+        let mut app = init_app(NoTy, FixedUpdate);
+        app.insert_resource(ResimulationBudget {
+            max_ticks: Some(2),
+            ..default()
+        });
+        app.update();
+
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(Tick(12).into());
+        app.update();
+
+        // Only 2 ticks get resimulated, then we jump back to the present
+        assert_eq!(*app.world().resource::<Tick>(), Tick(15));
+        assert_eq!(
+            **app.world().resource::<Runs>(),
+            [Tick(15), Tick(12), Tick(13), Tick(15)]
+        );
+        let events = app
+            .world()
+            .resource::<Events<ResimulationBudgetExceeded>>()
+            .iter_current_update_events()
+            .map(|e| (e.resimulated, e.skipped))
+            .collect::<Vec<_>>();
+        assert_eq!(events, [(2, 2)]);
+    }
+
+    #[test]
+    fn budget_catch_up() {
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct Resimulated(Vec<Tick>);
+
+        let mut app = init_app(NoTy, FixedUpdate);
+        app.insert_resource(ResimulationBudget {
+            max_ticks: Some(2),
+            fallback: BudgetFallback::CatchUp,
+            ..default()
+        })
+        .init_resource::<Resimulated>()
+        .add_systems(
+            FixedUpdate,
+            (|mut resimulated: ResMut<Resimulated>, tick: Res<Tick>| {
+                resimulated.push(*tick);
+            })
+            .run_if(is_resimulating),
+        );
+        app.update();
+
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(Tick(12).into());
+        app.update();
+
+        // Only 2 ticks get resimulated, the simulation continues from there
+        assert_eq!(*app.world().resource::<Tick>(), Tick(13));
+        assert_eq!(
+            **app.world().resource::<Runs>(),
+            [Tick(15), Tick(12), Tick(13), Tick(13)]
+        );
+
+        // The skipped ticks are resimulated on the next frame
+        app.update();
+        assert_eq!(*app.world().resource::<Tick>(), Tick(15));
+        assert_eq!(
+            **app.world().resource::<Runs>(),
+            [
+                Tick(15),
+                Tick(12),
+                Tick(13),
+                Tick(13),
+                Tick(14),
+                Tick(15),
+                Tick(15)
+            ]
+        );
+        // Only the ticks of the rollback itself count as resimulated
+        assert_eq!(
+            **app.world().resource::<Resimulated>(),
+            [Tick(12), Tick(13)]
+        );
+        assert!(!app.world().contains_resource::<RollbackContext>());
+    }
+
     #[test]
     fn rollback_uses_fixed_deltas() {
-        let mut app = init_app();
+        let mut app = init_app(NoTy, FixedUpdate);
         assert_eq!(*app.world().resource::<Tick>(), Tick(15));
 
         app.update();
@@ -402,7 +815,7 @@ mod tests {
 
     #[test]
     fn load_new_not_on_first_frame() {
-        let mut app = init_app();
+        let mut app = init_app(NoTy, FixedUpdate);
         assert_eq!(*app.world().resource::<Tick>(), Tick(15));
 
         #[derive(Resource, Deref, DerefMut, Default)]
@@ -439,7 +852,7 @@ mod tests {
 
     #[test]
     fn fast_forward() {
-        let mut app = init_app();
+        let mut app = init_app(NoTy, FixedUpdate);
         assert_eq!(*app.world().resource::<Tick>(), Tick(15));
 
         app.update();
@@ -466,30 +879,62 @@ pub struct StoreScheduleLabel(Interned<dyn ScheduleLabel>);
 /// An extension trait for [`App`] adding functions to register rollback components
 pub trait RollbackApp {
     /// Register a predicted-only component
-    fn register_predicted_component<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
-    >(
+    fn register_predicted_component<T: Component + Clone + Debug + PartialEq>(
         &mut self,
     ) -> &mut Self;
     /// Register an authoritative component
     fn register_authoritative_component<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
+        T: Component<Mutability: MutWrite<T>> + Clone + Debug + PartialEq,
     >(
         &mut self,
     ) -> &mut Self;
     /// Register a predicted-only resource
     fn register_predicted_resource<T: Resource + Clone + Debug>(&mut self) -> &mut Self;
+    /// Register an event written in the simulation as predicted. Events written on each tick are
+    /// kept in a history, they can be read once through a [`PredictedEventReader`], even when
+    /// they are written again while resimulating. Events written outside of the fixed main loop
+    /// aren't part of the simulation and aren't sent as predicted events. Regular
+    /// [`EventReader`]s still read every written event, including the ones written again while
+    /// resimulating.
+    fn register_predicted_event<E: Event + Clone + PartialEq>(&mut self) -> &mut Self;
+    /// Register a state whose [`State`] and [`NextState`] are predicted. Loading the state during
+    /// a rollback doesn't run any transition schedules. To resimulate transitions, run the
+    /// [`StateTransition`](bevy::state::state::StateTransition) schedule as part of the
+    /// simulation. Transitions applied while resimulating only update the state, when the
+    /// resimulated state differs from the state before the rollback, the state is queued in
+    /// [`NextState`] so the next transition exits the old state and enters the new one.
+    fn register_predicted_state<S: FreelyMutableState>(&mut self) -> &mut Self;
+
+    /// Register a predicted-only component holding entities. Stored values are mapped with its
+    /// [`MapEntities`] implementation when entities are replaced, see [`RemapHistories`].
+    fn register_mapped_predicted_component<T: Component + MapEntities + Clone + Debug + PartialEq>(
+        &mut self,
+    ) -> &mut Self;
+    /// Register an authoritative component holding entities, see
+    /// [`register_mapped_predicted_component`](Self::register_mapped_predicted_component).
+    /// The component should be replicated with `replicate_mapped` for received values to be
+    /// mapped to client entities.
+    fn register_mapped_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + MapEntities + Clone + Debug + PartialEq,
+    >(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Register a relationship like [`ChildOf`] as a predicted component. Only the relationship
+    /// is stored, its [`RelationshipTarget`](bevy::ecs::relationship::RelationshipTarget) is
+    /// updated by the relationship hooks when loading.
+    fn register_predicted_relationship<R: Relationship + Clone + Debug + PartialEq>(
+        &mut self,
+    ) -> &mut Self;
 
     /// Register a predicted-only component with a custom load function
-    fn register_predicted_component_with_load<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
-    >(
+    fn register_predicted_component_with_load<T: Component + Clone + Debug + PartialEq>(
         &mut self,
         load_fn: LoadFn<T>,
     ) -> &mut Self;
     /// Register an authoritative component with a custom load function
     fn register_authoritative_component_with_load<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
+        T: Component<Mutability: MutWrite<T>> + Clone + Debug + PartialEq,
     >(
         &mut self,
         load_fn: LoadFn<T>,
@@ -499,12 +944,60 @@ pub trait RollbackApp {
         &mut self,
         load_fn: LoadFn<T>,
     ) -> &mut Self;
+
+    /// Register a predicted-only component whose history is stored as serialized bytes.
+    /// Useful for components that can't or shouldn't be cloned, equality is checked on the bytes.
+    /// Values are stored with `postcard`, which doesn't support `deserialize_any` (untagged or
+    /// flattened serde types), values that fail to (de)serialize are logged and not loaded.
+    fn register_serialized_predicted_component<
+        T: Component + Serialize + DeserializeOwned + Debug,
+    >(
+        &mut self,
+    ) -> &mut Self;
+    /// Register an authoritative component whose history is stored as serialized bytes
+    fn register_serialized_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + Serialize + DeserializeOwned + Debug,
+    >(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Register a component using the policy it declares through [`RollbackComponent`]
+    fn register_rollback_component<T: RollbackComponent>(&mut self) -> &mut Self;
+
+    /// Register a predicted-only component by the type path it has in the [`AppTypeRegistry`].
+    /// The type needs to reflect `Component` and `PartialEq`, values are cloned and compared
+    /// through reflection. Types reflecting `Default` are checked for `PartialEq` on registration,
+    /// other types panic the first time their values are compared.
+    fn register_reflected_predicted_component(&mut self, type_path: &str) -> &mut Self;
+
+    /// Register an authoritative component through its registration in the [`AppTypeRegistry`],
+    /// with the same requirements as [`register_reflected_predicted_component`](Self::register_reflected_predicted_component)
+    fn register_reflected_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + TypePath,
+    >(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Keep the last value received for an authoritative component on entities that aren't
+    /// predicted, which seeds their histories when they get [promoted](promote_predicted) instead
+    /// of the current value. This replaces the command functions of the component.
+    fn register_promotable_component<T: Component<Mutability: MutWrite<T>> + Clone>(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Register a component to be recorded by the [`SessionRecorderPlugin`] and replayed by the
+    /// [`SessionReplayPlugin`]. Authoritative components need to be registered for their received
+    /// values to be recorded, other components are recorded when they change on replicated
+    /// entities. Values are keyed by their [`TypePath`], which can be pinned with the
+    /// `#[type_path]` and `#[type_name]` attributes to keep recordings loadable when the type is
+    /// moved.
+    fn record_component<T: Component + Serialize + DeserializeOwned + TypePath>(
+        &mut self,
+    ) -> &mut Self;
 }
 
 impl RollbackApp for App {
-    fn register_predicted_component<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
-    >(
+    fn register_predicted_component<T: Component + Clone + Debug + PartialEq>(
         &mut self,
     ) -> &mut Self {
         // Register component to rollback component registry
@@ -517,11 +1010,12 @@ impl RollbackApp for App {
         self
     }
     fn register_authoritative_component<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
+        T: Component<Mutability: MutWrite<T>> + Clone + Debug + PartialEq,
     >(
         &mut self,
     ) -> &mut Self {
         self.register_predicted_component::<T>();
+        mark_authoritative::<T>(self);
 
         self.set_marker_fns::<Predicted, T>(
             history::write_authoritative_history,
@@ -530,6 +1024,9 @@ impl RollbackApp for App {
     }
     fn register_predicted_resource<T: Resource + Clone + Debug>(&mut self) -> &mut Self {
         self.world_mut().init_resource::<ResourceHistory<T>>();
+        history::register_snapshot_resource::<T>(self.world_mut());
+        #[cfg(feature = "dump")]
+        dump::register_resource::<T>(self.world_mut());
 
         // Register store systems
         let store_schedule = **self.world().resource::<StoreScheduleLabel>();
@@ -549,12 +1046,108 @@ impl RollbackApp for App {
             store_schedule,
             predicted_resource::append_history::<T>.in_set(RollbackStoreSet),
         )
+        .add_systems(
+            RollbackSchedule::Reset,
+            predicted_resource::clear_history::<T>,
+        )
+    }
+
+    fn register_predicted_event<E: Event + Clone + PartialEq>(&mut self) -> &mut Self {
+        self.add_event::<E>()
+            .add_event::<PredictedEvent<E>>()
+            .init_resource::<predicted_event::PredictedEventHistory<E>>();
+
+        let store_schedule = **self.world().resource::<StoreScheduleLabel>();
+        self.add_systems(
+            RollbackSchedule::PreRollback,
+            predicted_event::save_previous_events::<E>,
+        )
+        .add_systems(
+            RunFixedMainLoop,
+            predicted_event::skip_unsimulated_events::<E>
+                .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+        )
+        .add_systems(
+            store_schedule,
+            predicted_event::store_events::<E>.in_set(RollbackStoreSet),
+        )
+        .add_systems(RollbackSchedule::Reset, predicted_event::clear_events::<E>)
+    }
+
+    fn register_predicted_state<S: FreelyMutableState>(&mut self) -> &mut Self {
+        self.init_resource::<predicted_state::StateHistory<S>>()
+            .add_event::<StateTransitionEvent<S>>()
+            .add_systems(
+                StateTransition,
+                predicted_state::apply_resimulated_transition::<S>
+                    .run_if(is_resimulating)
+                    .before(StateTransitionSteps::DependentTransitions),
+            );
+
+        let store_schedule = **self.world().resource::<StoreScheduleLabel>();
+        self.add_systems(
+            RollbackSchedule::PreRollback,
+            predicted_state::save_state_before_rollback::<S>,
+        )
+        .add_systems(
+            RollbackSchedule::Rollback,
+            predicted_state::load_state::<S>.in_set(RollbackLoadSet),
+        )
+        .add_systems(
+            RollbackSchedule::BackToPresent,
+            predicted_state::queue_resimulated_transition::<S>,
+        )
+        .add_systems(
+            store_schedule,
+            predicted_state::store_state::<S>.in_set(RollbackStoreSet),
+        )
+        .add_systems(RollbackSchedule::Reset, predicted_state::clear_states::<S>)
     }
 
-    fn register_predicted_component_with_load<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
+    fn register_mapped_predicted_component<
+        T: Component + MapEntities + Clone + Debug + PartialEq,
     >(
         &mut self,
+    ) -> &mut Self {
+        // Register component to rollback component registry
+        let mut registry = self
+            .world_mut()
+            .remove_resource::<RollbackRegistry>()
+            .unwrap();
+        registry.register_mapped::<T>(self.world_mut());
+        self.world_mut().insert_resource(registry);
+        self
+    }
+
+    fn register_mapped_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + MapEntities + Clone + Debug + PartialEq,
+    >(
+        &mut self,
+    ) -> &mut Self {
+        self.register_mapped_predicted_component::<T>();
+        mark_authoritative::<T>(self);
+
+        self.set_marker_fns::<Predicted, T>(
+            history::write_authoritative_history,
+            history::remove_authoritative_history::<T>,
+        )
+    }
+
+    fn register_predicted_relationship<R: Relationship + Clone + Debug + PartialEq>(
+        &mut self,
+    ) -> &mut Self {
+        // Register component to rollback component registry
+        let mut registry = self
+            .world_mut()
+            .remove_resource::<RollbackRegistry>()
+            .unwrap();
+        registry.register_relationship::<R>(self.world_mut());
+        self.world_mut().insert_resource(registry);
+        self
+    }
+
+    fn register_predicted_component_with_load<T: Component + Clone + Debug + PartialEq>(
+        &mut self,
         load_fn: LoadFn<T>,
     ) -> &mut Self {
         // Register component to rollback component registry
@@ -568,12 +1161,13 @@ impl RollbackApp for App {
     }
 
     fn register_authoritative_component_with_load<
-        T: Component<Mutability = Mutable> + Clone + Debug + PartialEq,
+        T: Component<Mutability: MutWrite<T>> + Clone + Debug + PartialEq,
     >(
         &mut self,
         load_fn: LoadFn<T>,
     ) -> &mut Self {
         self.register_predicted_component_with_load::<T>(load_fn);
+        mark_authoritative::<T>(self);
 
         self.set_marker_fns::<Predicted, T>(
             history::write_authoritative_history,
@@ -586,6 +1180,9 @@ impl RollbackApp for App {
         _: LoadFn<T>,
     ) -> &mut Self {
         self.world_mut().init_resource::<ResourceHistory<T>>();
+        history::register_snapshot_resource::<T>(self.world_mut());
+        #[cfg(feature = "dump")]
+        dump::register_resource::<T>(self.world_mut());
 
         // Register store systems
         let store_schedule = **self.world().resource::<StoreScheduleLabel>();
@@ -597,9 +1194,111 @@ impl RollbackApp for App {
             store_schedule,
             predicted_resource::append_history::<T>.in_set(RollbackStoreSet),
         )
+        .add_systems(
+            RollbackSchedule::Reset,
+            predicted_resource::clear_history::<T>,
+        )
+    }
+
+    fn register_serialized_predicted_component<
+        T: Component + Serialize + DeserializeOwned + Debug,
+    >(
+        &mut self,
+    ) -> &mut Self {
+        // Register component to rollback component registry
+        let mut registry = self
+            .world_mut()
+            .remove_resource::<RollbackRegistry>()
+            .unwrap();
+        registry.register_serialized::<T>(self.world_mut());
+        self.world_mut().insert_resource(registry);
+        self
+    }
+
+    fn register_serialized_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + Serialize + DeserializeOwned + Debug,
+    >(
+        &mut self,
+    ) -> &mut Self {
+        self.register_serialized_predicted_component::<T>();
+        mark_authoritative::<T>(self);
+
+        self.set_marker_fns::<Predicted, T>(
+            history::write_serialized_authoritative_history,
+            history::remove_authoritative_history::<T>,
+        )
+    }
+
+    fn register_rollback_component<T: RollbackComponent>(&mut self) -> &mut Self {
+        T::register_rollback(self);
+        self
+    }
+
+    fn register_reflected_predicted_component(&mut self, type_path: &str) -> &mut Self {
+        let type_registry = self.world().resource::<AppTypeRegistry>().clone();
+        let type_registry = type_registry.read();
+        let Some(registration) = type_registry.get_with_type_path(type_path) else {
+            panic!("{type_path} is not registered in the type registry");
+        };
+
+        // Register component to rollback component registry
+        let mut registry = self
+            .world_mut()
+            .remove_resource::<RollbackRegistry>()
+            .unwrap();
+        registry.register_reflect(self.world_mut(), registration);
+        self.world_mut().insert_resource(registry);
+        self
+    }
+
+    fn register_reflected_authoritative_component<
+        T: Component<Mutability: MutWrite<T>> + TypePath,
+    >(
+        &mut self,
+    ) -> &mut Self {
+        self.register_reflected_predicted_component(T::type_path());
+        mark_authoritative::<T>(self);
+
+        self.set_marker_fns::<Predicted, T>(
+            history::write_reflected_authoritative_history,
+            history::remove_authoritative_history::<T>,
+        )
+    }
+
+    fn register_promotable_component<T: Component<Mutability: MutWrite<T>> + Clone>(
+        &mut self,
+    ) -> &mut Self {
+        let id = self.world_mut().register_component::<T>();
+        let confirmed_id = self
+            .world_mut()
+            .register_component::<history::ConfirmedValue<T>>();
+        self.world_mut()
+            .resource_mut::<RollbackRegistry>()
+            .confirmed
+            .insert(id, confirmed_id);
+
+        self.set_command_fns::<T>(
+            history::write_confirmed::<T>,
+            history::remove_confirmed::<T>,
+        )
+    }
+
+    fn record_component<T: Component + Serialize + DeserializeOwned + TypePath>(
+        &mut self,
+    ) -> &mut Self {
+        recording::register::<T>(self);
+        self
     }
 }
 
+fn mark_authoritative<T: Component>(app: &mut App) {
+    let id = app.world_mut().register_component::<T>();
+    app.world_mut()
+        .resource_mut::<RollbackRegistry>()
+        .authoritative
+        .insert(id);
+}
+
 /// A marker component for predicted entities
 #[derive(Component, Default)]
 #[require(history::PredictedHistory, AuthoritativeHistory)]
@@ -614,7 +1313,7 @@ fn remove_histories(mut world: DeferredWorld, ctx: HookContext) {
 }
 
 /// Data for a tick
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum TickData<T> {
     /// There is a value for the tick
     Value(T),
@@ -641,6 +1340,9 @@ pub enum RollbackSchedule {
     PostResimulation,
     /// This schedule is executed when the world is back to the present
     BackToPresent,
+    /// This schedule is executed by [`ResetRollback`] when the client disconnects or connects,
+    /// histories that aren't stored on entities are cleared here
+    Reset,
 }
 
 /// A resource specifying the maximum number of rollback frames that should be stored.
@@ -678,6 +1380,102 @@ impl RollbackFrames {
     }
 }
 
+/// How often the state of predicted components is stored, defaults to every tick.
+/// With an interval of N, values are only stored on ticks that are a multiple of N,
+/// and rollbacks start from the last checkpoint before the target tick, resimulating the ticks
+/// in between. This trades CPU time for memory, which matters when hosting many worlds.
+///
+/// Rollbacks reach back N - 1 ticks less than the [`RollbackFrames`] allow, so the checkpoint
+/// before the target is still in the history. The interval is capped to the rollback frames - 2.
+///
+/// Predicted resources are still stored every tick.
+#[derive(Resource, Clone, Copy)]
+pub struct RollbackCheckpoints(u32);
+
+impl Default for RollbackCheckpoints {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl RollbackCheckpoints {
+    /// Construct a `RollbackCheckpoints` storing state every `interval` ticks
+    pub fn new(interval: u32) -> Self {
+        Self(interval.max(1))
+    }
+
+    /// The number of ticks between checkpoints
+    pub fn interval(&self) -> u32 {
+        self.0
+    }
+
+    /// Check if state is stored on the tick
+    pub fn is_checkpoint(&self, tick: RepliconTick) -> bool {
+        tick.get().is_multiple_of(self.0)
+    }
+
+    /// Get the last checkpoint at or before the tick
+    pub fn checkpoint_before(&self, tick: RepliconTick) -> RepliconTick {
+        RepliconTick::new(tick.get() - tick.get() % self.0)
+    }
+
+    /// Get the first checkpoint at or after the tick
+    pub fn checkpoint_after(&self, tick: RepliconTick) -> RepliconTick {
+        let before = self.checkpoint_before(tick);
+        if before == tick {
+            tick
+        } else {
+            RepliconTick::new(before.get() + self.0)
+        }
+    }
+}
+
+/// A limit on how much is resimulated in a single frame, unlimited by default.
+/// When a rollback exceeds the budget, the remaining ticks are handled by the [`BudgetFallback`].
+#[derive(Resource, Clone, Copy, Default, Debug)]
+pub struct ResimulationBudget {
+    /// The maximum number of ticks to resimulate per frame
+    pub max_ticks: Option<u32>,
+    /// The maximum time to spend resimulating per frame, checked before each resimulated tick
+    pub max_time: Option<Duration>,
+    /// What to do with the ticks that exceed the budget
+    pub fallback: BudgetFallback,
+}
+
+impl ResimulationBudget {
+    fn is_exceeded(&self, resimulated: u32, elapsed: Duration) -> bool {
+        self.max_ticks.is_some_and(|max| resimulated >= max)
+            || self.max_time.is_some_and(|max| elapsed >= max)
+    }
+}
+
+/// How ticks that exceed the [`ResimulationBudget`] are handled
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum BudgetFallback {
+    /// Skip the remaining ticks and jump to the present, like a rollback to a future tick
+    #[default]
+    FastForward,
+    /// Continue from the last resimulated tick, and simulate the remaining ticks over the next
+    /// frames within the budget of each frame. These ticks aren't part of the rollback,
+    /// [`is_resimulating`] is false for them.
+    CatchUp,
+}
+
+/// An event sent when a resimulation exceeded the [`ResimulationBudget`]
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ResimulationBudgetExceeded {
+    /// The number of ticks that were resimulated
+    pub resimulated: u32,
+    /// The number of ticks that were skipped or postponed
+    pub skipped: u32,
+    /// How the skipped ticks are handled
+    pub fallback: BudgetFallback,
+}
+
+/// Ticks that still need to be resimulated with [`BudgetFallback::CatchUp`]
+#[derive(Resource)]
+struct PendingCatchUp(u32);
+
 /// The tick to roll back to, reset to [`None`] after a rollback is triggered
 #[derive(Resource, Deref, DerefMut, Default)]
 pub struct RollbackTarget(Option<RepliconTick>);
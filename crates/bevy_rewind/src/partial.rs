@@ -0,0 +1,207 @@
+use crate::{
+    Predicted, ResimulatingInstance, RollbackSchedule, RollbackStoreSet, RollbackTarget,
+    TickSource, calculate_rollback_target,
+};
+
+use std::marker::PhantomData;
+
+use bevy::{
+    app::RunFixedMainLoop,
+    ecs::{entity::EntityHashSet, entity_disabling::Disabled, system::SystemParam},
+    platform::collections::HashSet,
+    prelude::*,
+};
+use bevy_replicon::client::{
+    confirm_history::EntityReplicated, server_mutate_ticks::MutateTickReceived,
+};
+
+/// A plugin that only rolls back the predicted entities affected by a correction.
+///
+/// Entities that received data from the server are rolled back along with all entities in the
+/// same [`RollbackGroup`]. All other predicted entities are listed in [`SkipResimulation`] during
+/// the rollback, they are not loaded or stored and keep their current predicted state.
+/// Simulation systems should skip them with [`Resimulated`]. Entities without a group are only
+/// rolled back when they are corrected themselves.
+///
+/// Rollbacks triggered by a [`MutateTickReceived`] or a manually set [`RollbackTarget`]
+/// affect all entities.
+pub struct PartialRollbackPlugin<Tick: TickSource>(PhantomData<Tick>);
+
+impl<Tick: TickSource> Default for PartialRollbackPlugin<Tick> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Tick: TickSource> Plugin for PartialRollbackPlugin<Tick> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AffectedEntities>()
+            .add_systems(
+                RunFixedMainLoop,
+                collect_affected.before(calculate_rollback_target::<Tick>),
+            )
+            .add_systems(
+                RollbackSchedule::PreRollback,
+                skip_unaffected
+                    .after(RollbackStoreSet)
+                    .run_if(not(resource_exists::<ResimulatingInstance>)),
+            )
+            .add_systems(RollbackSchedule::BackToPresent, restore_skipped);
+    }
+}
+
+/// A group of entities that depend on each other, and need to be rolled back together.
+/// Groups can be declared by hand, or computed from physics islands or contacts.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RollbackGroup(pub u64);
+
+/// The predicted entities that are not affected by the current rollback, only present during
+/// partial rollbacks. Their histories are not loaded or stored, so they keep their present state.
+#[derive(Resource, Default, Deref, Debug)]
+pub struct SkipResimulation(pub EntityHashSet);
+
+/// A system parameter checking if an entity is resimulated, use it in simulation systems to skip
+/// entities in [`SkipResimulation`]. All entities are resimulated outside of partial rollbacks.
+#[derive(SystemParam)]
+pub struct Resimulated<'w> {
+    skipped: Option<Res<'w, SkipResimulation>>,
+}
+
+impl Resimulated<'_> {
+    /// Check if the entity is simulated on the current tick
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.skipped
+            .as_ref()
+            .is_none_or(|skipped| !skipped.contains(&entity))
+    }
+}
+
+/// The entities and groups that received corrections since the last rollback
+#[derive(Resource, Default)]
+struct AffectedEntities {
+    all: bool,
+    entities: EntityHashSet,
+    groups: HashSet<RollbackGroup>,
+}
+
+fn collect_affected(
+    mut replicated: EventReader<EntityReplicated>,
+    mut mutate_ticks: EventReader<MutateTickReceived>,
+    target: Res<RollbackTarget>,
+    mut affected: ResMut<AffectedEntities>,
+    groups: Query<&RollbackGroup>,
+) {
+    // Global confirmations and manual rollbacks can affect any entity
+    if mutate_ticks.read().count() > 0 || target.is_some() {
+        affected.all = true;
+    }
+
+    for event in replicated.read() {
+        match groups.get(event.entity) {
+            Ok(&group) => {
+                affected.groups.insert(group);
+            }
+            Err(_) => {
+                affected.entities.insert(event.entity);
+            }
+        }
+    }
+}
+
+fn skip_unaffected(
+    mut commands: Commands,
+    mut affected: ResMut<AffectedEntities>,
+    q: Query<
+        (Entity, Option<&RollbackGroup>),
+        (With<Predicted>, Or<(With<Disabled>, Without<Disabled>)>),
+    >,
+) {
+    if !affected.all {
+        let skipped = q
+            .iter()
+            .filter(|&(entity, group)| match group {
+                Some(group) => !affected.groups.contains(group),
+                None => !affected.entities.contains(&entity),
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        commands.insert_resource(SkipResimulation(skipped));
+    }
+
+    affected.all = false;
+    affected.entities.clear();
+    affected.groups.clear();
+}
+
+pub(crate) fn restore_skipped(mut commands: Commands) {
+    commands.remove_resource::<SkipResimulation>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Tick, init_app};
+
+    use bevy::ecs::schedule::ScheduleLabel;
+    use bevy_replicon::{
+        client::server_mutate_ticks::MutateTickReceived, shared::replicon_tick::RepliconTick,
+    };
+
+    #[derive(Component, Default)]
+    struct Simulated(u32);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    #[test]
+    fn only_resimulates_affected() {
+        let mut app = init_app(Store, FixedUpdate);
+        app.add_plugins(PartialRollbackPlugin::<Tick>::default())
+            .add_systems(
+                FixedUpdate,
+                |mut q: Query<(Entity, &mut Simulated)>, resimulated: Resimulated| {
+                    for (entity, mut simulated) in q.iter_mut() {
+                        if resimulated.contains(entity) {
+                            simulated.0 += 1;
+                        }
+                    }
+                },
+            );
+
+        let world = app.world_mut();
+        let corrected = world
+            .spawn((Predicted, Simulated::default(), RollbackGroup(1)))
+            .id();
+        let same_group = world
+            .spawn((Predicted, Simulated::default(), RollbackGroup(1)))
+            .id();
+        let other_group = world
+            .spawn((Predicted, Simulated::default(), RollbackGroup(2)))
+            .id();
+        let ungrouped = world.spawn((Predicted, Simulated::default())).id();
+
+        world.send_event(EntityReplicated {
+            entity: corrected,
+            tick: RepliconTick::new(14),
+        });
+        app.update();
+
+        // Ticks 14 and 15 are resimulated for the affected group, followed by the regular tick
+        let world = app.world();
+        assert_eq!(3, world.get::<Simulated>(corrected).unwrap().0);
+        assert_eq!(3, world.get::<Simulated>(same_group).unwrap().0);
+        assert_eq!(1, world.get::<Simulated>(other_group).unwrap().0);
+        assert_eq!(1, world.get::<Simulated>(ungrouped).unwrap().0);
+        assert!(!world.contains_resource::<SkipResimulation>());
+
+        // Global confirmations resimulate all entities
+        app.world_mut().send_event(MutateTickReceived {
+            tick: RepliconTick::new(14),
+        });
+        app.update();
+        let world = app.world();
+        assert_eq!(6, world.get::<Simulated>(corrected).unwrap().0);
+        assert_eq!(4, world.get::<Simulated>(other_group).unwrap().0);
+        assert_eq!(4, world.get::<Simulated>(ungrouped).unwrap().0);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{RollbackContext, RollbackFrames, StoreFor};
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    ecs::{event::EventCursor, system::SystemParam},
+    prelude::*,
+};
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// The history of a predicted event, tracking the events sent on each tick
+#[derive(Resource)]
+pub(crate) struct PredictedEventHistory<E: Event> {
+    ticks: BTreeMap<RepliconTick, Vec<E>>,
+    // The events of the ticks still to be resimulated, from before the rollback
+    previous: BTreeMap<RepliconTick, Vec<E>>,
+    // Skips the events written outside of the simulation
+    cursor: EventCursor<E>,
+}
+
+impl<E: Event> Default for PredictedEventHistory<E> {
+    fn default() -> Self {
+        Self {
+            ticks: default(),
+            previous: default(),
+            cursor: default(),
+        }
+    }
+}
+
+/// A predicted event, sent once for each logical event written in the simulation
+#[derive(Event, Clone, Debug)]
+pub struct PredictedEvent<E: Event> {
+    /// The tick the event was written on
+    pub tick: RepliconTick,
+    /// The event itself
+    pub event: E,
+}
+
+/// A system param for reading predicted events registered with
+/// [`register_predicted_event`](crate::RollbackApp::register_predicted_event).
+/// Each event is only read once, even if it is written again while resimulating.
+#[derive(SystemParam)]
+pub struct PredictedEventReader<'w, 's, E: Event> {
+    reader: EventReader<'w, 's, PredictedEvent<E>>,
+}
+
+impl<E: Event> PredictedEventReader<'_, '_, E> {
+    /// Iterate over the events that haven't been read yet
+    pub fn read(&mut self) -> impl Iterator<Item = &E> + '_ {
+        self.reader.read().map(|e| &e.event)
+    }
+
+    /// Iterate over the events that haven't been read yet, along with their tick
+    pub fn read_with_tick(&mut self) -> impl Iterator<Item = (RepliconTick, &E)> + '_ {
+        self.reader.read().map(|e| (e.tick, &e.event))
+    }
+}
+
+/// Skip the events written since the last simulated tick, they aren't part of the simulation
+pub(crate) fn skip_unsimulated_events<E: Event>(
+    events: Res<Events<E>>,
+    mut history: ResMut<PredictedEventHistory<E>>,
+) {
+    history.cursor = events.get_cursor_current();
+}
+
+/// Copy the events written this tick to the history, leaving them in [`Events`] for regular
+/// readers. Only events that weren't written on this tick before a rollback are sent.
+pub(crate) fn store_events<E: Event + Clone + PartialEq>(
+    events: Res<Events<E>>,
+    mut history: ResMut<PredictedEventHistory<E>>,
+    mut predicted: EventWriter<PredictedEvent<E>>,
+    tick: Res<StoreFor>,
+    frames: Res<RollbackFrames>,
+) {
+    let PredictedEventHistory {
+        ticks,
+        previous,
+        cursor,
+    } = &mut *history;
+    let written = cursor.read(&events).cloned().collect::<Vec<_>>();
+
+    let mut old = previous.remove(&**tick).unwrap_or_default();
+    for event in &written {
+        match old.iter().position(|o| o == event) {
+            Some(index) => {
+                old.swap_remove(index);
+            }
+            None => {
+                predicted.write(PredictedEvent {
+                    tick: **tick,
+                    event: event.clone(),
+                });
+            }
+        }
+    }
+    // Ticks up to this one are resimulated, only postponed ticks are still compared
+    *previous = previous.split_off(&RepliconTick::new(tick.get() + 1));
+
+    if !written.is_empty() {
+        ticks.insert(**tick, written);
+    }
+
+    let min = RepliconTick::new(tick.get().saturating_sub(frames.history_size() as u32));
+    *ticks = ticks.split_off(&min);
+}
+
+/// Set the events of the ticks being resimulated aside
+pub(crate) fn save_previous_events<E: Event>(
+    mut history: ResMut<PredictedEventHistory<E>>,
+    context: Res<RollbackContext>,
+) {
+    let mut previous = history.ticks.split_off(&context.start);
+    // Ticks postponed by an earlier rollback are resimulated as part of this one
+    previous.append(&mut history.previous);
+    history.previous = previous;
+}
+
+pub(crate) fn clear_events<E: Event>(mut history: ResMut<PredictedEventHistory<E>>) {
+    *history = default();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        BudgetFallback, ResimulationBudget, RollbackApp, RollbackTarget,
+        tests::{Tick, init_app},
+    };
+
+    use super::*;
+
+    use bevy::{ecs::schedule::ScheduleLabel, time::TimeUpdateStrategy};
+
+    #[derive(Event, Clone, PartialEq, Debug)]
+    struct Shot(u32);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct ShotAt(Option<Tick>);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct Received(Vec<u32>);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct ReceivedRaw(Vec<u32>);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Sim;
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct SimLast;
+
+    fn shot_app() -> App {
+        let mut app = init_app(SimLast, Sim);
+        app.register_predicted_event::<Shot>()
+            .init_resource::<ShotAt>()
+            .init_resource::<Received>()
+            .init_resource::<ReceivedRaw>()
+            .add_systems(FixedUpdate, |world: &mut World| world.run_schedule(Sim))
+            .add_systems(
+                Sim,
+                (
+                    |mut shots: EventWriter<Shot>, shot_at: Res<ShotAt>, tick: Res<Tick>| {
+                        if **shot_at == Some(*tick) {
+                            shots.write(Shot(tick.0));
+                        }
+                    },
+                    |world: &mut World| world.run_schedule(SimLast),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    |mut reader: PredictedEventReader<Shot>, mut received: ResMut<Received>| {
+                        received.extend(reader.read().map(|shot| shot.0));
+                    },
+                    |mut reader: EventReader<Shot>, mut received: ResMut<ReceivedRaw>| {
+                        received.extend(reader.read().map(|shot| shot.0));
+                    },
+                ),
+            );
+        app
+    }
+
+    #[test]
+    fn sends_events_once() {
+        let mut app = shot_app();
+        app.insert_resource(Tick(14));
+
+        **app.world_mut().resource_mut::<ShotAt>() = Some(Tick(14));
+        app.update();
+        assert_eq!(**app.world().resource::<Received>(), [14]);
+
+        // Writing the same event while resimulating doesn't send it again
+        app.insert_resource(Tick(15));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(**app.world().resource::<Received>(), [14]);
+
+        // Events that weren't written before the rollback are sent
+        **app.world_mut().resource_mut::<ShotAt>() = Some(Tick(15));
+        app.insert_resource(Tick(16));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(**app.world().resource::<Received>(), [14, 15]);
+        // Regular readers still receive every written event
+        assert_eq!(**app.world().resource::<ReceivedRaw>(), [14, 14, 15]);
+    }
+
+    #[test]
+    fn ignores_events_outside_simulation() {
+        let mut app = shot_app();
+
+        app.world_mut().send_event(Shot(0));
+        app.update();
+        assert!(app.world().resource::<Received>().is_empty());
+        assert_eq!(**app.world().resource::<ReceivedRaw>(), [0]);
+    }
+
+    #[test]
+    fn sends_events_once_while_catching_up() {
+        let mut app = shot_app();
+        app.insert_resource(ResimulationBudget {
+            max_ticks: Some(1),
+            fallback: BudgetFallback::CatchUp,
+            ..default()
+        });
+
+        **app.world_mut().resource_mut::<ShotAt>() = Some(Tick(15));
+        app.update();
+        assert_eq!(**app.world().resource::<Received>(), [15]);
+
+        // Only rollbacks and catch-ups run the simulation from now on
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+
+        // Tick 15 is postponed by the budget
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(*app.world().resource::<Tick>(), Tick(14));
+
+        // Catching up writes the event again, but it was already sent before the rollback
+        app.update();
+        assert_eq!(*app.world().resource::<Tick>(), Tick(15));
+        assert_eq!(**app.world().resource::<Received>(), [15]);
+        assert_eq!(**app.world().resource::<ReceivedRaw>(), [15, 15]);
+    }
+}
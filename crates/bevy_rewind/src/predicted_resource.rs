@@ -43,6 +43,15 @@ impl<T> ResourceHistory<T> {
         self.list.is_empty()
     }
 
+    /// Iterate over the stored values and the ticks they were stored for
+    pub fn iter(&self) -> impl Iterator<Item = (RepliconTick, &TickData<T>)> {
+        let first_tick = (self.last_tick + 1).saturating_sub(self.list.len() as u32);
+        self.list
+            .iter()
+            .enumerate()
+            .map(move |(i, value)| (RepliconTick::new(first_tick + i as u32), value))
+    }
+
     /// Get the value for the specified tick. You always want to load the value stored on
     /// the previous tick
     pub fn get(&self, previous_tick: RepliconTick) -> &TickData<T> {
@@ -131,19 +140,25 @@ pub(super) fn append_history<T: Resource + Clone + Debug>(
     hist.last_tick = tick.get();
 }
 
-/// A system that saves the initial spawn value if history is empty
-// TODO: Figure something out for reconnecting
+/// A system that saves the initial spawn value if history is empty,
+/// which is also the case after the history is cleared by a reset
 pub(super) fn save_initial<T: Resource + Clone + Debug>(
-    t: Res<T>,
+    t: Option<Res<T>>,
     mut history: ResMut<ResourceHistory<T>>,
     tick: Res<StoreFor>,
 ) {
-    if history.is_empty() {
+    if let Some(t) = t
+        && history.is_empty()
+    {
         history.last_tick = tick.get();
         history.list.push_back(TickData::Value(t.clone()));
     }
 }
 
+pub(super) fn clear_history<T: Resource>(mut history: ResMut<ResourceHistory<T>>) {
+    *history = default();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,239 @@
+use crate::{LoadFrom, RollbackFrames, StoreFor};
+
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, state::state::FreelyMutableState};
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// The history of a predicted state
+#[derive(Resource)]
+pub(crate) struct StateHistory<S: FreelyMutableState> {
+    ticks: BTreeMap<RepliconTick, StoredState<S>>,
+    // The state from before the rollback
+    before: Option<S>,
+}
+
+impl<S: FreelyMutableState> Default for StateHistory<S> {
+    fn default() -> Self {
+        Self {
+            ticks: default(),
+            before: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StoredState<S: FreelyMutableState> {
+    state: Option<S>,
+    next: Option<NextState<S>>,
+}
+
+pub(crate) fn store_state<S: FreelyMutableState>(
+    mut history: ResMut<StateHistory<S>>,
+    state: Option<Res<State<S>>>,
+    next: Option<Res<NextState<S>>>,
+    tick: Res<StoreFor>,
+    frames: Res<RollbackFrames>,
+) {
+    history.ticks.insert(
+        **tick,
+        StoredState {
+            state: state.map(|state| state.get().clone()),
+            next: next.map(|next| next.clone()),
+        },
+    );
+
+    let min = RepliconTick::new(tick.get().saturating_sub(frames.history_size() as u32));
+    history.ticks = history.ticks.split_off(&min);
+}
+
+/// Keep the state from before the rollback, to compare it with the resimulated state
+pub(crate) fn save_state_before_rollback<S: FreelyMutableState>(
+    mut history: ResMut<StateHistory<S>>,
+    state: Option<Res<State<S>>>,
+) {
+    history.before = state.map(|state| state.get().clone());
+}
+
+/// Load the state directly, which doesn't run any transition schedules
+pub(crate) fn load_state<S: FreelyMutableState>(world: &mut World) {
+    let tick = **world.resource::<LoadFrom>();
+    let Some(stored) = world
+        .resource::<StateHistory<S>>()
+        .ticks
+        .get(&tick)
+        .cloned()
+    else {
+        return;
+    };
+
+    match stored.state {
+        Some(state) => world.insert_resource(State::new(state)),
+        None => {
+            world.remove_resource::<State<S>>();
+        }
+    }
+    match stored.next {
+        Some(next) => world.insert_resource(next),
+        None => {
+            world.remove_resource::<NextState<S>>();
+        }
+    }
+}
+
+/// Apply transitions while resimulating without sending [`StateTransitionEvent`]s, so no
+/// transition schedules run for states that are only passed through while resimulating
+///
+/// [`StateTransitionEvent`]: bevy::state::state::StateTransitionEvent
+pub(crate) fn apply_resimulated_transition<S: FreelyMutableState>(
+    state: Option<ResMut<State<S>>>,
+    next: Option<ResMut<NextState<S>>>,
+) {
+    let (Some(mut state), Some(mut next)) = (state, next) else {
+        return;
+    };
+    if let NextState::Pending(entered) = std::mem::take(&mut *next) {
+        *state = State::new(entered);
+    }
+}
+
+/// Transition from the state before the rollback to the resimulated state, when they differ.
+/// The state is set back and the resimulated state is queued, unless the simulation already
+/// queued another transition, so the next [`StateTransition`] runs the transition schedules.
+///
+/// [`StateTransition`]: bevy::state::state::StateTransition
+pub(crate) fn queue_resimulated_transition<S: FreelyMutableState>(
+    mut commands: Commands,
+    mut history: ResMut<StateHistory<S>>,
+    state: Option<ResMut<State<S>>>,
+    next: Option<Res<NextState<S>>>,
+) {
+    let (Some(before), Some(mut state)) = (history.before.take(), state) else {
+        return;
+    };
+    if before == *state.get() {
+        return;
+    }
+
+    let entered = state.get().clone();
+    *state = State::new(before);
+    if !matches!(next.as_deref(), Some(NextState::Pending(_))) {
+        commands.insert_resource(NextState::Pending(entered));
+    }
+}
+
+pub(crate) fn clear_states<S: FreelyMutableState>(mut history: ResMut<StateHistory<S>>) {
+    *history = default();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        RollbackApp, RollbackTarget,
+        tests::{Tick, init_app},
+    };
+
+    use bevy::{ecs::schedule::ScheduleLabel, prelude::*, state::state::StateTransition};
+    use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+    #[derive(States, Clone, PartialEq, Eq, Hash, Debug, Default)]
+    enum Phase {
+        #[default]
+        Countdown,
+        Playing,
+    }
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct StartAt(Option<Tick>);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct Entered(u32);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct Exited(u32);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Sim;
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct SimLast;
+
+    fn rollback_to(app: &mut App, tick: u32) {
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(tick));
+        app.update();
+    }
+
+    #[test]
+    fn runs_net_transitions_after_resimulating() {
+        let mut app = init_app(SimLast, Sim);
+        app.init_state::<Phase>()
+            .register_predicted_state::<Phase>()
+            .init_resource::<StartAt>()
+            .init_resource::<Entered>()
+            .init_resource::<Exited>()
+            .insert_resource(Tick(14))
+            .add_systems(FixedUpdate, |world: &mut World| world.run_schedule(Sim))
+            .add_systems(
+                Sim,
+                (
+                    |mut next: ResMut<NextState<Phase>>,
+                     start_at: Res<StartAt>,
+                     tick: Res<Tick>| {
+                        if **start_at == Some(*tick) {
+                            next.set(Phase::Playing);
+                        }
+                    },
+                    |world: &mut World| world.run_schedule(StateTransition),
+                    |world: &mut World| world.run_schedule(SimLast),
+                )
+                    .chain(),
+            )
+            .add_systems(OnEnter(Phase::Playing), |mut entered: ResMut<Entered>| {
+                **entered += 1;
+            })
+            .add_systems(OnExit(Phase::Playing), |mut exited: ResMut<Exited>| {
+                **exited += 1;
+            });
+
+        **app.world_mut().resource_mut::<StartAt>() = Some(Tick(15));
+        app.update();
+        app.insert_resource(Tick(15));
+        app.update();
+        assert_eq!(1, **app.world().resource::<Entered>());
+
+        // Resimulating the same transition restores the state without entering it again
+        app.insert_resource(Tick(16));
+        rollback_to(&mut app, 15);
+        assert_eq!(1, **app.world().resource::<Entered>());
+        assert_eq!(
+            Phase::Playing,
+            *app.world().resource::<State<Phase>>().get()
+        );
+
+        // A transition on a different tick ends in the same state, so it isn't entered again
+        **app.world_mut().resource_mut::<StartAt>() = Some(Tick(16));
+        rollback_to(&mut app, 15);
+        assert_eq!(1, **app.world().resource::<Entered>());
+        assert_eq!(0, **app.world().resource::<Exited>());
+
+        // Undoing the transition exits the state
+        **app.world_mut().resource_mut::<StartAt>() = None;
+        rollback_to(&mut app, 15);
+        assert_eq!(1, **app.world().resource::<Entered>());
+        assert_eq!(1, **app.world().resource::<Exited>());
+        assert_eq!(
+            Phase::Countdown,
+            *app.world().resource::<State<Phase>>().get()
+        );
+
+        // Redoing it enters the state again
+        **app.world_mut().resource_mut::<StartAt>() = Some(Tick(16));
+        rollback_to(&mut app, 15);
+        assert_eq!(2, **app.world().resource::<Entered>());
+        assert_eq!(1, **app.world().resource::<Exited>());
+        assert_eq!(
+            Phase::Playing,
+            *app.world().resource::<State<Phase>>().get()
+        );
+    }
+}
@@ -0,0 +1,709 @@
+use crate::{
+    Predicted, RollbackFrames, TickSource,
+    history::{RollbackRegistry, remove_history_internal, write_erased_authoritative_history},
+};
+
+use std::{
+    fmt::Display,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use bevy::{
+    ecs::{
+        component::ComponentId,
+        entity::{Entities, EntityHashMap, EntityHashSet},
+        system::Command,
+    },
+    platform::collections::HashMap,
+    prelude::*,
+    ptr::Ptr,
+    time::{TimeSystem, TimeUpdateStrategy},
+};
+use bevy_replicon::{
+    client::{
+        ClientSystems,
+        confirm_history::{ConfirmHistory, EntityReplicated},
+        server_mutate_ticks::{MutateTickReceived, ServerMutateTicks},
+    },
+    prelude::ClientState,
+    shared::{
+        replication::deferred_entity::{DeferredChanges, DeferredEntity},
+        replicon_tick::RepliconTick,
+    },
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// A recorded client session, see [`SessionRecorderPlugin`] and [`SessionReplayPlugin`]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SessionRecording {
+    /// The recorded frames, in order
+    pub frames: Vec<RecordedFrame>,
+}
+
+/// Everything that was received during a single frame
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct RecordedFrame {
+    /// The real time that passed since the previous frame
+    pub delta: Duration,
+    /// The tick at the start of the frame
+    pub tick: u32,
+    /// The entities the server confirmed a tick for
+    pub replicated: Vec<(u64, u32)>,
+    /// The ticks for which all mutations were received
+    pub mutate_ticks: Vec<u32>,
+    /// The received authoritative values
+    pub authoritative: Vec<RecordedValue>,
+    /// The changes to components registered with
+    /// [`RollbackApp::record_component`](crate::RollbackApp::record_component)
+    pub components: Vec<RecordedValue>,
+    /// The replicated entities that were despawned
+    pub despawned: Vec<u64>,
+    /// The inputs used for simulated ticks, see [`SessionRecorder::record_input`]
+    pub inputs: Vec<RecordedValue>,
+}
+
+/// A serialized component value
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RecordedValue {
+    /// The entity in the recorded session, as returned by [`Entity::to_bits`]
+    pub entity: u64,
+    /// The [type path](TypePath) of the component or input, which stays the same across builds
+    pub component: String,
+    /// The tick the value was received for
+    pub tick: u32,
+    /// The serialized value, `None` if the component was removed
+    pub value: Option<Vec<u8>>,
+}
+
+/// An error while saving or loading a [`SessionRecording`]
+#[derive(Debug)]
+pub enum RecordingError {
+    /// Failed to read or write the file
+    Io(std::io::Error),
+    /// Failed to serialize or deserialize the recording
+    Postcard(postcard::Error),
+}
+
+impl Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "io error: {e}"),
+            RecordingError::Postcard(e) => write!(f, "invalid recording: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl SessionRecording {
+    /// Write the recording to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        let bytes = postcard::to_allocvec(self).map_err(RecordingError::Postcard)?;
+        std::fs::write(path, bytes).map_err(RecordingError::Io)
+    }
+
+    /// Load a recording from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let bytes = std::fs::read(path).map_err(RecordingError::Io)?;
+        postcard::from_bytes(&bytes).map_err(RecordingError::Postcard)
+    }
+}
+
+/// The set in which received data is recorded and replayed.
+/// Runs in [`PreUpdate`] after replicon received its messages.
+#[derive(SystemSet, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct SessionRecordSet;
+
+/// A plugin that records everything the client received that affects rollback,
+/// along with changes to components registered with
+/// [`RollbackApp::record_component`](crate::RollbackApp::record_component).
+///
+/// Authoritative components are only recorded if they are registered for recording as well.
+pub struct SessionRecorderPlugin<Tick: TickSource>(PhantomData<Tick>);
+
+impl<Tick: TickSource> Default for SessionRecorderPlugin<Tick> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Tick: TickSource> Plugin for SessionRecorderPlugin<Tick> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionRecorder>()
+            .init_resource::<RecordRegistry>()
+            .configure_sets(PreUpdate, SessionRecordSet.after(ClientSystems::Receive))
+            .add_systems(First, start_frame::<Tick>.after(TimeSystem))
+            .add_systems(PreUpdate, record_received.in_set(SessionRecordSet));
+    }
+}
+
+/// The state of a session recording
+#[derive(Resource, Default)]
+pub struct SessionRecorder {
+    recording: SessionRecording,
+    received: Mutex<Vec<RecordedValue>>,
+    known: EntityHashSet,
+}
+
+impl SessionRecorder {
+    /// Get the recording so far
+    pub fn recording(&self) -> &SessionRecording {
+        &self.recording
+    }
+
+    /// Take the recording so far, the next frames will be recorded to a new recording
+    pub fn take_recording(&mut self) -> SessionRecording {
+        std::mem::take(&mut self.recording)
+    }
+
+    /// Record the input an entity used on a tick, so it can be replayed with
+    /// [`SessionReplay::inputs`]. Used by `bevy_rewind_input` with its `recording` feature.
+    pub fn record_input<T: Serialize + TypePath>(
+        &mut self,
+        entity: Entity,
+        tick: RepliconTick,
+        input: &T,
+    ) {
+        let frame = self.frame_mut();
+        frame.inputs.push(RecordedValue {
+            entity: entity.to_bits(),
+            component: T::type_path().into(),
+            tick: tick.get(),
+            value: Some(serialize(input)),
+        });
+    }
+
+    fn frame_mut(&mut self) -> &mut RecordedFrame {
+        if self.recording.frames.is_empty() {
+            self.recording.frames.push(default());
+        }
+        self.recording.frames.last_mut().unwrap()
+    }
+}
+
+/// A command that saves the current [`SessionRecording`] to a file
+pub struct SaveSessionRecording(pub PathBuf);
+
+impl Command for SaveSessionRecording {
+    fn apply(self, world: &mut World) {
+        let Some(recorder) = world.get_resource::<SessionRecorder>() else {
+            warn!("Trying to save a session recording, but the session isn't being recorded");
+            return;
+        };
+        if let Err(e) = recorder.recording().save(&self.0) {
+            error!(
+                "Failed to save session recording to {}: {e}",
+                self.0.display()
+            );
+        }
+    }
+}
+
+/// A plugin that replays a [`SessionRecording`], driving the rollback logic with the recorded
+/// data instead of a server connection. Should be added instead of a messaging backend.
+///
+/// Once all frames are replayed, an [`AppExit`] event is sent.
+pub struct SessionReplayPlugin<Tick: TickSource> {
+    recording: SessionRecording,
+    phantom: PhantomData<Tick>,
+}
+
+impl<Tick: TickSource> SessionReplayPlugin<Tick> {
+    /// Construct a `SessionReplayPlugin` replaying the recording
+    pub fn new(recording: SessionRecording) -> Self {
+        Self {
+            recording,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Tick: TickSource> Plugin for SessionReplayPlugin<Tick> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SessionReplay {
+            recording: self.recording.clone(),
+            next_frame: 0,
+            entities: default(),
+            inputs: default(),
+        })
+        .init_resource::<RecordRegistry>()
+        .configure_sets(PreUpdate, SessionRecordSet.after(ClientSystems::Receive))
+        .add_systems(Startup, connect)
+        .add_systems(First, advance_replay::<Tick>.before(TimeSystem))
+        .add_systems(PreUpdate, replay_frame.in_set(SessionRecordSet));
+    }
+}
+
+/// The state of a session replay
+#[derive(Resource)]
+pub struct SessionReplay {
+    recording: SessionRecording,
+    next_frame: usize,
+    entities: EntityHashMap<Entity>,
+    /// The inputs recorded during the current frame, for the replaying entities
+    inputs: Vec<(Entity, RecordedValue)>,
+}
+
+impl SessionReplay {
+    /// Check if all frames were replayed
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    /// Get the entity replaying an entity from the recorded session
+    pub fn entity(&self, recorded: Entity) -> Option<Entity> {
+        self.entities.get(&recorded).copied()
+    }
+
+    /// Iterate over the inputs of type `T` that were used on the tick during the current frame
+    pub fn inputs<T: DeserializeOwned + TypePath>(
+        &self,
+        tick: RepliconTick,
+    ) -> impl Iterator<Item = (Entity, T)> + '_ {
+        let name = T::type_path();
+        self.inputs
+            .iter()
+            .filter(move |(_, input)| input.tick == tick.get() && input.component == name)
+            .filter_map(|(entity, input)| Some((*entity, deserialize(input.value.as_deref()?))))
+    }
+
+    fn map_entity(&mut self, world: &mut World, recorded: u64, predicted: bool) -> Entity {
+        *self
+            .entities
+            .entry(Entity::from_bits(recorded))
+            .or_insert_with(|| {
+                let mut entity = world.spawn(ConfirmHistory::new(RepliconTick::default()));
+                if predicted {
+                    entity.insert(Predicted);
+                }
+                entity.id()
+            })
+    }
+}
+
+/// Functions to record and replay a component
+#[derive(Clone, Copy)]
+struct RecordFns {
+    name: &'static str,
+    component_id: ComponentId,
+    /// SAFETY: The pointer MUST point to a value of the component
+    serialize: unsafe fn(Ptr) -> Vec<u8>,
+    with_value: fn(&[u8], &mut dyn FnMut(Ptr)),
+    apply: fn(&mut World, Entity, Option<&[u8]>),
+}
+
+/// The components registered for recording
+#[derive(Resource, Default)]
+pub(crate) struct RecordRegistry {
+    ids: HashMap<ComponentId, usize>,
+    names: HashMap<&'static str, usize>,
+    components: Vec<RecordFns>,
+}
+
+impl RecordRegistry {
+    fn get(&self, id: ComponentId) -> Option<&RecordFns> {
+        self.ids.get(&id).map(|&index| &self.components[index])
+    }
+
+    fn get_by_name(&self, name: &str) -> Option<&RecordFns> {
+        self.names.get(name).map(|&index| &self.components[index])
+    }
+}
+
+pub(crate) fn register<T: Component + Serialize + DeserializeOwned + TypePath>(app: &mut App) {
+    let component_id = app.world_mut().register_component::<T>();
+    let name = T::type_path();
+
+    let mut registry = app.world_mut().get_resource_or_init::<RecordRegistry>();
+    if registry.ids.contains_key(&component_id) {
+        return;
+    }
+    let index = registry.components.len();
+    registry.ids.insert(component_id, index);
+    registry.names.insert(name, index);
+    registry.components.push(RecordFns {
+        name,
+        component_id,
+        serialize: |ptr| serialize(unsafe { ptr.deref::<T>() }),
+        with_value: |bytes, f| {
+            let value = deserialize::<T>(bytes);
+            f(Ptr::from(&value));
+        },
+        apply: |world, entity, bytes| {
+            let mut entity = world.entity_mut(entity);
+            match bytes {
+                Some(bytes) => {
+                    entity.insert(deserialize::<T>(bytes));
+                }
+                None => {
+                    entity.remove::<T>();
+                }
+            }
+        },
+    });
+
+    app.add_systems(
+        PreUpdate,
+        record_changes::<T>
+            .run_if(resource_exists::<SessionRecorder>)
+            .in_set(SessionRecordSet),
+    );
+}
+
+fn serialize<T: Serialize>(value: &T) -> Vec<u8> {
+    postcard::to_allocvec(value).expect("serializing a recorded value should never fail")
+}
+
+fn deserialize<T: DeserializeOwned>(bytes: &[u8]) -> T {
+    postcard::from_bytes(bytes).expect("recorded values should be deserializable")
+}
+
+/// Record a received authoritative value, if the session is being recorded
+pub(crate) fn record_authoritative(
+    world: &World,
+    entity: Entity,
+    component_id: ComponentId,
+    tick: RepliconTick,
+    value: Option<Ptr>,
+) {
+    let Some(recorder) = world.get_resource::<SessionRecorder>() else {
+        return;
+    };
+    let Some(fns) = world
+        .get_resource::<RecordRegistry>()
+        .and_then(|registry| registry.get(component_id))
+    else {
+        warn_once!(
+            "Authoritative component {:?} is not registered for recording",
+            component_id,
+        );
+        return;
+    };
+
+    recorder.received.lock().unwrap().push(RecordedValue {
+        entity: entity.to_bits(),
+        component: fns.name.into(),
+        tick: tick.get(),
+        // SAFETY: The value belongs to the component registered for this ComponentId
+        value: value.map(|value| unsafe { (fns.serialize)(value) }),
+    });
+}
+
+fn start_frame<Tick: TickSource>(
+    mut recorder: ResMut<SessionRecorder>,
+    time: Res<Time<Real>>,
+    tick: Option<Res<Tick>>,
+) {
+    recorder.recording.frames.push(RecordedFrame {
+        delta: time.delta(),
+        tick: tick.map(|tick| (*tick).into().get()).unwrap_or_default(),
+        ..default()
+    });
+}
+
+fn record_received(
+    mut recorder: ResMut<SessionRecorder>,
+    mut replicated: EventReader<EntityReplicated>,
+    mut mutate_ticks: EventReader<MutateTickReceived>,
+    added: Query<Entity, Added<ConfirmHistory>>,
+    entities: &Entities,
+) {
+    let recorder = &mut *recorder;
+    let received = std::mem::take(&mut *recorder.received.lock().unwrap());
+
+    let mut despawned = Vec::new();
+    recorder.known.retain(|&entity| {
+        let exists = entities.contains(entity);
+        if !exists {
+            despawned.push(entity.to_bits());
+        }
+        exists
+    });
+    recorder.known.extend(added.iter());
+
+    let frame = recorder.frame_mut();
+    frame.replicated.extend(
+        replicated
+            .read()
+            .map(|e| (e.entity.to_bits(), e.tick.get())),
+    );
+    frame
+        .mutate_ticks
+        .extend(mutate_ticks.read().map(|e| e.tick.get()));
+    frame.authoritative.extend(received);
+    frame.despawned.extend(despawned);
+}
+
+fn record_changes<T: Component + Serialize + TypePath>(
+    mut recorder: ResMut<SessionRecorder>,
+    changed: Query<(Entity, &T), (Changed<T>, With<ConfirmHistory>)>,
+    mut removed: RemovedComponents<T>,
+    replicated: Query<(), With<ConfirmHistory>>,
+) {
+    let name = T::type_path();
+    let frame = recorder.frame_mut();
+    let tick = frame.tick;
+
+    // Despawned entities are recorded separately
+    let removed = removed
+        .read()
+        .filter(|&entity| replicated.contains(entity))
+        .map(|entity| (entity, None));
+    let changed = changed
+        .iter()
+        .map(|(entity, value)| (entity, Some(serialize(value))));
+    frame
+        .components
+        .extend(removed.chain(changed).map(|(entity, value)| RecordedValue {
+            entity: entity.to_bits(),
+            component: name.into(),
+            tick,
+            value,
+        }));
+}
+
+fn connect(state: Option<ResMut<NextState<ClientState>>>) {
+    if let Some(mut state) = state {
+        state.set(ClientState::Connected);
+    }
+}
+
+fn advance_replay<Tick: TickSource>(
+    mut commands: Commands,
+    replay: Res<SessionReplay>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(frame) = replay.recording.frames.get(replay.next_frame) else {
+        exit.write(AppExit::Success);
+        return;
+    };
+
+    *strategy = TimeUpdateStrategy::ManualDuration(frame.delta);
+    commands.insert_resource(Tick::from(RepliconTick::new(frame.tick)));
+}
+
+fn replay_frame(world: &mut World) {
+    world.resource_scope(|world, mut replay: Mut<SessionReplay>| {
+        let Some(frame) = replay.recording.frames.get(replay.next_frame).cloned() else {
+            return;
+        };
+        replay.next_frame += 1;
+
+        let inputs = frame
+            .inputs
+            .into_iter()
+            .map(|input| (replay.map_entity(world, input.entity, false), input))
+            .collect();
+        replay.inputs = inputs;
+
+        world.resource_scope(|world, registry: Mut<RecordRegistry>| {
+            for value in frame.authoritative {
+                let Some(fns) = registry.get_by_name(&value.component) else {
+                    warn!("Recorded component {} is not registered", value.component);
+                    continue;
+                };
+                let entity = replay.map_entity(world, value.entity, true);
+                replay_authoritative(world, entity, fns, &value);
+            }
+
+            for value in frame.components {
+                let Some(fns) = registry.get_by_name(&value.component) else {
+                    warn!("Recorded component {} is not registered", value.component);
+                    continue;
+                };
+                let entity = replay.map_entity(world, value.entity, false);
+                (fns.apply)(world, entity, value.value.as_deref());
+            }
+        });
+
+        for (entity, tick) in frame.replicated {
+            let entity = replay.map_entity(world, entity, false);
+            let tick = RepliconTick::new(tick);
+            if let Some(mut confirm) = world.get_mut::<ConfirmHistory>(entity) {
+                confirm.confirm(tick);
+            }
+            world.send_event(EntityReplicated { entity, tick });
+        }
+
+        for tick in frame.mutate_ticks {
+            let tick = RepliconTick::new(tick);
+            if let Some(mut mutate_ticks) = world.get_resource_mut::<ServerMutateTicks>() {
+                mutate_ticks.confirm(tick, 1);
+            }
+            world.send_event(MutateTickReceived { tick });
+        }
+
+        for entity in frame.despawned {
+            if let Some(entity) = replay.entities.remove(&Entity::from_bits(entity)) {
+                world.despawn(entity);
+            }
+        }
+    });
+}
+
+fn replay_authoritative(world: &mut World, entity: Entity, fns: &RecordFns, value: &RecordedValue) {
+    let Some(component) = world
+        .get_resource::<RollbackRegistry>()
+        .and_then(|registry| {
+            let &index = registry.ids.get(&fns.component_id)?;
+            Some(registry.components[index].clone())
+        })
+    else {
+        warn!(
+            "Recorded component {} is not registered for rollback",
+            fns.name
+        );
+        return;
+    };
+    let frames = world
+        .get_resource::<RollbackFrames>()
+        .copied()
+        .unwrap_or_default();
+    let tick = RepliconTick::new(value.tick);
+
+    let mut changes = DeferredChanges::default();
+    let mut entity = DeferredEntity::new(world.entity_mut(entity), &mut changes);
+    match &value.value {
+        Some(bytes) => (fns.with_value)(bytes, &mut |ptr| {
+            // SAFETY: The value was deserialized as the component's type
+            unsafe {
+                write_erased_authoritative_history(
+                    fns.component_id,
+                    &mut entity,
+                    tick,
+                    ptr,
+                    frames,
+                    &component,
+                );
+            }
+        }),
+        None => remove_history_internal(fns.component_id, tick, &mut entity),
+    }
+    entity.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthoritativeHistory, RollbackApp, history::test_utils::*, tests::Tick};
+
+    use bevy::time::TimePlugin;
+
+    #[derive(Component, TypePath, Serialize, Deserialize, Clone, PartialEq, Debug)]
+    struct P(u16);
+
+    fn init_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .init_resource::<Tick>()
+            .init_resource::<RollbackFrames>()
+            .init_resource::<ServerMutateTicks>()
+            .add_event::<EntityReplicated>()
+            .add_event::<MutateTickReceived>()
+            .add_event::<AppExit>();
+
+        let mut registry = RollbackRegistry::default();
+        registry.register::<P>(app.world_mut());
+        app.insert_resource(registry);
+
+        app
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let mut app = init_app();
+        app.add_plugins(SessionRecorderPlugin::<Tick>::default())
+            .record_component::<P>();
+        let comp_p = app.world_mut().register_component::<P>();
+
+        // A replicated entity with a recorded component
+        let other = app
+            .world_mut()
+            .spawn((ConfirmHistory::new(r_tick(0)), P(7)))
+            .id();
+        app.update();
+
+        // A predicted entity receiving an authoritative value
+        let entity = app
+            .world_mut()
+            .spawn((Predicted, ConfirmHistory::new(r_tick(0))))
+            .id();
+        record_authoritative(
+            app.world(),
+            entity,
+            comp_p,
+            r_tick(1),
+            Some(Ptr::from(&P(3))),
+        );
+        app.world_mut().send_event(EntityReplicated {
+            entity,
+            tick: r_tick(1),
+        });
+        app.update();
+
+        app.world_mut().despawn(other);
+        app.update();
+
+        let recording = app
+            .world_mut()
+            .resource_mut::<SessionRecorder>()
+            .take_recording();
+        assert_eq!(3, recording.frames.len());
+        let name = P::type_path().to_string();
+        assert_eq!(
+            vec![RecordedValue {
+                entity: other.to_bits(),
+                component: name.clone(),
+                tick: 0,
+                value: Some(serialize(&P(7))),
+            }],
+            recording.frames[0].components
+        );
+        assert_eq!(
+            vec![RecordedValue {
+                entity: entity.to_bits(),
+                component: name,
+                tick: 1,
+                value: Some(serialize(&P(3))),
+            }],
+            recording.frames[1].authoritative
+        );
+        assert_eq!(vec![(entity.to_bits(), 1)], recording.frames[1].replicated);
+        assert_eq!(vec![other.to_bits()], recording.frames[2].despawned);
+
+        // Replay the recording in a new app
+        let mut app = init_app();
+        app.add_plugins(SessionReplayPlugin::<Tick>::new(recording))
+            .record_component::<P>();
+
+        app.update();
+        let replay = app.world().resource::<SessionReplay>();
+        let replayed_other = replay.entity(other).unwrap();
+        assert_eq!(Some(&P(7)), app.world().get::<P>(replayed_other));
+
+        app.update();
+        let replay = app.world().resource::<SessionReplay>();
+        let replayed = replay.entity(entity).unwrap();
+        let world = app.world();
+        let history = world.get::<AuthoritativeHistory>(replayed).unwrap();
+        assert_eq!(Some(&P(3)), history[&comp_p].get(1).deref::<P>().value());
+        assert!(
+            world
+                .get::<ConfirmHistory>(replayed)
+                .unwrap()
+                .contains(r_tick(1))
+        );
+
+        app.update();
+        let replay = app.world().resource::<SessionReplay>();
+        assert!(replay.is_finished());
+        assert!(app.world().get_entity(replayed_other).is_err());
+
+        app.update();
+        assert!(!app.world().resource::<Events<AppExit>>().is_empty());
+    }
+}
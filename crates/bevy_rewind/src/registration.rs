@@ -0,0 +1,126 @@
+use bevy::{prelude::*, reflect::FromType};
+
+/// A component that knows how it should be registered for rollback.
+/// Usually implemented through `#[derive(Rollback)]`.
+pub trait RollbackComponent: Component {
+    /// Register this component's rollback logic to the app
+    fn register_rollback(app: &mut App);
+}
+
+/// Type data for [`RollbackComponent`]s, used by [`RollbackRegistrationPlugin`] to find components
+/// to register. Add it to a type with `#[reflect(RollbackComponent)]`.
+#[derive(Clone)]
+pub struct ReflectRollbackComponent {
+    register: fn(&mut App),
+}
+
+impl ReflectRollbackComponent {
+    /// Register the component's rollback logic to the app
+    pub fn register(&self, app: &mut App) {
+        (self.register)(app);
+    }
+}
+
+impl<T: RollbackComponent> FromType<T> for ReflectRollbackComponent {
+    fn from_type() -> Self {
+        Self {
+            register: T::register_rollback,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub mod macro_exports {
+    pub use bevy::app::App;
+}
+
+/// A plugin that registers all types with [`ReflectRollbackComponent`] type data once the app is
+/// finished building, like components deriving [`Rollback`](crate::Rollback) with
+/// `#[reflect(RollbackComponent)]` that are registered through `App::register_type`.
+pub struct RollbackRegistrationPlugin;
+
+impl Plugin for RollbackRegistrationPlugin {
+    fn build(&self, _: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        let type_registry = app.world().resource::<AppTypeRegistry>().clone();
+        let components = type_registry
+            .read()
+            .iter_with_data::<ReflectRollbackComponent>()
+            .map(|(_, data)| data.clone())
+            .collect::<Vec<_>>();
+
+        for component in components {
+            component.register(app);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Rollback, RollbackApp, RollbackPlugin, history::RollbackRegistry, tests::Tick};
+
+    use super::{ReflectRollbackComponent, RollbackRegistrationPlugin};
+
+    use std::marker::PhantomData;
+
+    use bevy::{ecs::schedule::ScheduleLabel, prelude::*, state::app::StatesPlugin};
+    use bevy_replicon::prelude::*;
+
+    #[derive(Component, Reflect, Rollback, Clone, PartialEq, Debug)]
+    #[rollback(predicted)]
+    #[reflect(RollbackComponent)]
+    struct A(u8);
+
+    #[derive(Component, Rollback, Clone, PartialEq, Debug)]
+    #[rollback(predicted, load = load_b)]
+    struct B(u8);
+
+    fn load_b(
+        _: Option<&B>,
+        predicted: Option<&B>,
+        dst: crate::ExistingOrUninit<B>,
+        _: Commands,
+        _: Entity,
+    ) {
+        dst.write(B(predicted.unwrap().0 + 1));
+    }
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct NoTy;
+
+    fn init_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            StatesPlugin,
+            RepliconSharedPlugin::default(),
+            RollbackPlugin::<Tick> {
+                store_schedule: NoTy.intern(),
+                rollback_schedule: NoTy.intern(),
+                phantom: PhantomData,
+            },
+        ));
+        app
+    }
+
+    #[test]
+    fn register_derived() {
+        let mut app = init_app();
+        app.register_rollback_component::<B>();
+
+        let comp_b = app.world_mut().register_component::<B>();
+        let registry = app.world().resource::<RollbackRegistry>();
+        assert!(registry.ids.contains_key(&comp_b));
+    }
+
+    #[test]
+    fn register_from_type_registry() {
+        let mut app = init_app();
+        app.register_type::<A>();
+        RollbackRegistrationPlugin.finish(&mut app);
+
+        let comp_a = app.world_mut().register_component::<A>();
+        let registry = app.world().resource::<RollbackRegistry>();
+        assert!(registry.ids.contains_key(&comp_a));
+    }
+}
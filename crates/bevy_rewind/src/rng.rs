@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use crate::{ResourceHistory, RollbackApp};
+
+use bevy::prelude::*;
+use bevy_replicon::{prelude::*, shared::replicon_tick::RepliconTick};
+use serde::{Deserialize, Serialize};
+
+/// A plugin adding the [`RollbackRng`] as a predicted resource, and replicating the
+/// [`RollbackRngSeed`]. Add it to both the server and clients, after the
+/// [`RollbackPlugin`](crate::RollbackPlugin).
+pub struct RollbackRngPlugin;
+
+impl Plugin for RollbackRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackRng>()
+            .register_predicted_resource::<RollbackRng>()
+            .replicate::<RollbackRngSeed>()
+            .add_observer(seed_rng);
+    }
+}
+
+/// A deterministic random number generator, registered as a predicted resource by the
+/// [`RollbackRngPlugin`], so draws are repeated exactly when resimulating.
+///
+/// Draws from the resource itself depend on the order in which systems use it. Streams derived
+/// with [`for_tick`](Self::for_tick), [`for_entity`](Self::for_entity) or
+/// [`for_key`](Self::for_key) only depend on the seed and their inputs instead.
+///
+/// Only the derived streams match between the server and clients. The state of the resource
+/// itself isn't replicated, every app starts drawing from the seed when it's seeded, so a client
+/// joining after the server drew from it gets different numbers.
+///
+/// The generator is seeded by spawning a [`RollbackRngSeed`] on the server.
+#[derive(Resource, Clone, Debug, Default, Deref, DerefMut)]
+pub struct RollbackRng {
+    seed: u64,
+    #[deref]
+    stream: RngStream,
+}
+
+impl RollbackRng {
+    /// Construct a `RollbackRng` from a seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            stream: RngStream(seed),
+        }
+    }
+
+    /// The seed of the generator
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Derive a stream for a tick
+    pub fn for_tick(&self, tick: impl Into<RepliconTick>) -> RngStream {
+        self.for_key(0, tick)
+    }
+
+    /// Derive a stream for an entity on a tick. Entities differ between the server and clients,
+    /// on clients use the server entity from the
+    /// [`ServerEntityMap`](bevy_replicon::client::server_entity_map::ServerEntityMap)
+    /// for results to match the server.
+    pub fn for_entity(&self, entity: Entity, tick: impl Into<RepliconTick>) -> RngStream {
+        self.for_key(entity.to_bits(), tick)
+    }
+
+    /// Derive a stream for an arbitrary key on a tick
+    pub fn for_key(&self, key: u64, tick: impl Into<RepliconTick>) -> RngStream {
+        let tick = tick.into().get() as u64;
+        RngStream(mix(self.seed ^ mix(key ^ mix(tick))))
+    }
+}
+
+/// A stream of deterministic random numbers, based on `SplitMix64`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RngStream(u64);
+
+impl RngStream {
+    /// Get a random `u64`
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+
+    /// Get a random `u32`
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Get a random `f32` in the range `0..1`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Get a random `u32` in the range, panics if the range is empty
+    pub fn range(&mut self, range: Range<u32>) -> u32 {
+        assert!(
+            !range.is_empty(),
+            "Cannot pick a number from an empty range"
+        );
+        let len = (range.end - range.start) as u64;
+        range.start + ((self.next_u32() as u64 * len) >> 32) as u32
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A replicated component seeding the [`RollbackRng`] when inserted.
+/// Spawn it on the server along with `Replicated` to seed clients as well.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RollbackRngSeed(pub u64);
+
+fn seed_rng(
+    trigger: Trigger<OnInsert, RollbackRngSeed>,
+    mut commands: Commands,
+    seeds: Query<&RollbackRngSeed>,
+    history: Option<ResMut<ResourceHistory<RollbackRng>>>,
+) {
+    let Ok(&RollbackRngSeed(seed)) = seeds.get(trigger.target()) else {
+        return;
+    };
+    commands.insert_resource(RollbackRng::new(seed));
+    // Rollbacks shouldn't load the generator from before it was seeded
+    if let Some(mut history) = history {
+        *history = default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TickData;
+
+    #[test]
+    fn streams_are_deterministic() {
+        let mut rng = RollbackRng::new(5);
+        let entity = Entity::from_raw(3);
+        let first = rng.for_entity(entity, RepliconTick::new(10)).next_u64();
+
+        // Drawing from the resource doesn't affect derived streams
+        rng.next_u64();
+        assert_eq!(
+            first,
+            rng.for_entity(entity, RepliconTick::new(10)).next_u64()
+        );
+        assert_ne!(
+            first,
+            rng.for_entity(entity, RepliconTick::new(11)).next_u64()
+        );
+        assert_ne!(
+            first,
+            RollbackRng::new(6)
+                .for_entity(entity, RepliconTick::new(10))
+                .next_u64()
+        );
+
+        // Restoring a copy of the resource repeats its draws
+        let copy = rng.clone();
+        let draws = [rng.next_u64(), rng.next_u64()];
+        let mut rng = copy;
+        assert_eq!(draws, [rng.next_u64(), rng.next_u64()]);
+
+        let mut stream = rng.for_tick(RepliconTick::new(1));
+        for _ in 0..100 {
+            assert!((3..7).contains(&stream.range(3..7)));
+            assert!((0.0..1.0).contains(&stream.next_f32()));
+        }
+    }
+
+    #[test]
+    fn seeds_from_component() {
+        let mut world = World::new();
+        world.add_observer(seed_rng);
+        world.spawn(RollbackRngSeed(42));
+        world.flush();
+
+        assert_eq!(42, world.resource::<RollbackRng>().seed());
+    }
+
+    #[test]
+    fn seeding_resets_history() {
+        let mut world = World::new();
+        world.add_observer(seed_rng);
+        world.insert_resource(ResourceHistory::from_list(
+            3,
+            [TickData::Value(RollbackRng::new(1))],
+        ));
+        world.spawn(RollbackRngSeed(42));
+        world.flush();
+
+        assert!(world.resource::<ResourceHistory<RollbackRng>>().is_empty());
+    }
+
+    #[test]
+    fn plugin_registers_rng() {
+        #[derive(bevy::ecs::schedule::ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+        struct Store;
+
+        let mut app = crate::tests::init_app(Store, FixedUpdate);
+        app.add_plugins(RollbackRngPlugin);
+        app.world_mut().spawn(RollbackRngSeed(42));
+        app.update();
+
+        assert_eq!(42, app.world().resource::<RollbackRng>().seed());
+        assert!(
+            app.world()
+                .contains_resource::<ResourceHistory<RollbackRng>>()
+        );
+    }
+}
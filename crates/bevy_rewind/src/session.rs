@@ -0,0 +1,133 @@
+use crate::{
+    PendingCatchUp, RequestedRollback, RollbackSchedule, RollbackTarget,
+    history::{clear_histories, seed_predicted_histories},
+};
+
+use bevy::prelude::*;
+use bevy_replicon::{
+    client::{confirm_history::EntityReplicated, server_mutate_ticks::MutateTickReceived},
+    shared::replicon_tick::RepliconTick,
+};
+
+/// A command clearing all rollback state, so nothing from a previous session gets loaded.
+/// Clears the histories of all predicted entities and runs [`RollbackSchedule::Reset`], in which
+/// histories of resources, events and states are cleared.
+///
+/// This is queued automatically when the client disconnects or connects.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResetRollback;
+
+impl Command for ResetRollback {
+    fn apply(self, world: &mut World) {
+        **world.resource_mut::<RollbackTarget>() = None;
+        **world.resource_mut::<RequestedRollback>() = 0;
+        world.remove_resource::<PendingCatchUp>();
+        world.remove_resource::<SessionStart>();
+        world.remove_resource::<AwaitingSnapshot>();
+
+        clear_histories(world);
+        world.run_schedule(RollbackSchedule::Reset);
+    }
+}
+
+/// A resource present after connecting, until the first confirmed tick is received.
+/// There is nothing to roll back to while it is present.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct AwaitingSnapshot;
+
+/// The first tick confirmed by the server in the current session, rollbacks never go further
+/// back than this tick
+#[derive(Resource, Clone, Copy, Deref, Debug)]
+pub struct SessionStart(RepliconTick);
+
+pub(crate) fn reset_rollback(mut commands: Commands) {
+    commands.queue(ResetRollback);
+}
+
+pub(crate) fn await_snapshot(mut commands: Commands) {
+    commands.queue(ResetRollback);
+    commands.insert_resource(AwaitingSnapshot);
+}
+
+/// Start the session once the first snapshot is confirmed, seeding the predicted histories
+/// on the tick before it, which is the first tick that gets loaded
+pub(crate) fn start_session(
+    mut commands: Commands,
+    mut individual_confirms: EventReader<EntityReplicated>,
+    mut global_confirms: EventReader<MutateTickReceived>,
+) {
+    let Some(start) = individual_confirms
+        .read()
+        .map(|c| c.tick)
+        .chain(global_confirms.read().map(|c| c.tick))
+        .min()
+    else {
+        return;
+    };
+
+    commands.remove_resource::<AwaitingSnapshot>();
+    commands.insert_resource(SessionStart(start));
+    commands.queue(move |world: &mut World| {
+        seed_predicted_histories(world, RepliconTick::new(start.get().saturating_sub(1)));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Predicted, RollbackApp, RollbackFrames,
+        history::PredictedHistory,
+        tests::{Tick, init_app},
+    };
+
+    use bevy::ecs::schedule::ScheduleLabel;
+    use bevy_replicon::prelude::ClientState;
+
+    #[derive(Component, Clone, PartialEq, Debug)]
+    struct Speed(u32);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    #[test]
+    fn resets_between_sessions() {
+        let mut app = init_app(Store, FixedUpdate);
+        app.register_predicted_component::<Speed>()
+            .insert_resource(RollbackFrames(10))
+            .insert_resource(Tick(14))
+            .add_systems(FixedUpdate, |world: &mut World| world.run_schedule(Store));
+
+        let entity = app.world_mut().spawn((Predicted, Speed(1))).id();
+        app.update();
+
+        // Connecting clears the history of the previous session
+        app.insert_resource(Tick(18));
+        app.world_mut()
+            .resource_mut::<NextState<ClientState>>()
+            .set(ClientState::Connected);
+        app.update();
+        assert!(app.world().contains_resource::<AwaitingSnapshot>());
+        let speed_id = app.world().component_id::<Speed>().unwrap();
+        let history = &app.world().get::<PredictedHistory>(entity).unwrap()[&speed_id];
+        assert!(history.get(14).value().is_none());
+
+        // The first snapshot seeds the history, so the first rollback keeps the component
+        app.insert_resource(Tick(20));
+        app.world_mut().send_event(MutateTickReceived {
+            tick: RepliconTick::new(17),
+        });
+        app.update();
+        assert!(!app.world().contains_resource::<AwaitingSnapshot>());
+        assert_eq!(17, app.world().resource::<SessionStart>().get());
+        assert_eq!(Some(&Speed(1)), app.world().get::<Speed>(entity));
+
+        // Rollbacks don't go back further than the start of the session
+        app.insert_resource(Tick(21));
+        app.world_mut().send_event(MutateTickReceived {
+            tick: RepliconTick::new(15),
+        });
+        app.update();
+        assert_eq!(4, **app.world().resource::<RequestedRollback>());
+    }
+}
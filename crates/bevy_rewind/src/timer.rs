@@ -0,0 +1,157 @@
+use crate::RollbackApp;
+
+use bevy::prelude::*;
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// A plugin registering the [`RollbackTimer`] as a predicted component.
+/// Add it after the [`RollbackPlugin`](crate::RollbackPlugin).
+pub struct RollbackTimerPlugin;
+
+impl Plugin for RollbackTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_predicted_component::<RollbackTimer>();
+    }
+}
+
+/// A timer counting ticks instead of time, registered as a predicted component by the
+/// [`RollbackTimerPlugin`].
+///
+/// The timer only stores the tick it started on, so all queries take the current tick. This
+/// makes them give the same answer when a tick is resimulated, unlike timers that are advanced
+/// with the delta of [`Time<Virtual>`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RollbackTimer {
+    start: RepliconTick,
+    duration: u32,
+    mode: TimerMode,
+}
+
+impl RollbackTimer {
+    /// Construct a `RollbackTimer` starting on a tick and running for `duration` ticks
+    pub fn new(start: impl Into<RepliconTick>, duration: u32, mode: TimerMode) -> Self {
+        Self {
+            start: start.into(),
+            duration: duration.max(1),
+            mode,
+        }
+    }
+
+    /// Construct a `RollbackTimer` that finishes once, for example a cooldown
+    pub fn once(start: impl Into<RepliconTick>, duration: u32) -> Self {
+        Self::new(start, duration, TimerMode::Once)
+    }
+
+    /// Construct a `RollbackTimer` that finishes every `duration` ticks
+    pub fn repeating(start: impl Into<RepliconTick>, duration: u32) -> Self {
+        Self::new(start, duration, TimerMode::Repeating)
+    }
+
+    /// Restart the timer on a tick
+    pub fn reset(&mut self, start: impl Into<RepliconTick>) {
+        self.start = start.into();
+    }
+
+    /// The tick the timer started on
+    pub fn start(&self) -> RepliconTick {
+        self.start
+    }
+
+    /// The duration of the timer in ticks
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// The mode of the timer
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    /// The number of ticks since the timer started, or since the last time a repeating timer
+    /// finished. Once timers stop counting when they finish.
+    pub fn elapsed(&self, tick: impl Into<RepliconTick>) -> u32 {
+        let since_start = self.since_start(tick);
+        match self.mode {
+            TimerMode::Once => since_start.min(self.duration),
+            TimerMode::Repeating => since_start % self.duration,
+        }
+    }
+
+    /// The number of ticks until the timer finishes
+    pub fn remaining(&self, tick: impl Into<RepliconTick>) -> u32 {
+        self.duration - self.elapsed(tick)
+    }
+
+    /// The fraction of the duration that has elapsed, in the range `0..=1`
+    pub fn fraction(&self, tick: impl Into<RepliconTick>) -> f32 {
+        self.elapsed(tick) as f32 / self.duration as f32
+    }
+
+    /// Check if the timer has finished at least once on or before the tick
+    pub fn finished(&self, tick: impl Into<RepliconTick>) -> bool {
+        self.since_start(tick) >= self.duration
+    }
+
+    /// Check if the timer finished exactly on the tick
+    pub fn just_finished_at(&self, tick: impl Into<RepliconTick>) -> bool {
+        let since_start = self.since_start(tick);
+        match self.mode {
+            TimerMode::Once => since_start == self.duration,
+            TimerMode::Repeating => since_start > 0 && since_start.is_multiple_of(self.duration),
+        }
+    }
+
+    /// The number of times the timer finished on or before the tick
+    pub fn times_finished(&self, tick: impl Into<RepliconTick>) -> u32 {
+        let times = self.since_start(tick) / self.duration;
+        match self.mode {
+            TimerMode::Once => times.min(1),
+            TimerMode::Repeating => times,
+        }
+    }
+
+    fn since_start(&self, tick: impl Into<RepliconTick>) -> u32 {
+        tick.into().get().saturating_sub(self.start.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(tick: u32) -> RepliconTick {
+        RepliconTick::new(tick)
+    }
+
+    #[test]
+    fn once() {
+        let timer = RollbackTimer::once(tick(10), 3);
+
+        assert_eq!(0, timer.elapsed(tick(8)));
+        assert!(!timer.finished(tick(12)));
+        assert_eq!(1, timer.remaining(tick(12)));
+        assert!(timer.just_finished_at(tick(13)));
+        assert!(timer.finished(tick(20)));
+        assert!(!timer.just_finished_at(tick(16)));
+        assert_eq!(1, timer.times_finished(tick(20)));
+        assert_eq!(1.0, timer.fraction(tick(20)));
+    }
+
+    #[test]
+    fn repeating() {
+        let mut timer = RollbackTimer::repeating(tick(10), 3);
+
+        assert!(!timer.just_finished_at(tick(10)));
+        assert!(timer.just_finished_at(tick(13)));
+        assert!(!timer.just_finished_at(tick(14)));
+        assert!(timer.just_finished_at(tick(16)));
+        assert_eq!(2, timer.times_finished(tick(17)));
+        assert_eq!(1, timer.elapsed(tick(17)));
+
+        // Querying a tick again, like when resimulating, gives the same answer
+        assert!(timer.just_finished_at(tick(13)));
+
+        timer.reset(tick(15));
+        assert!(!timer.finished(tick(16)));
+        assert!(timer.just_finished_at(tick(18)));
+    }
+}
@@ -11,14 +11,15 @@ use bevy::{
     prelude::*,
 };
 use bevy_replicon::{
-    prelude::{ClientState, Signature},
+    prelude::{ClientState, Replicated, Signature},
     shared::{
         replication::registry::{ReplicationRegistry, ctx::DespawnCtx},
         replicon_tick::RepliconTick,
     },
 };
 use bevy_rewind::{
-    Predicted, RollbackFrames, RollbackSchedule, RollbackStoreSet, StoreScheduleLabel, TickSource,
+    Predicted, RemapHistories, RollbackFrames, RollbackSchedule, RollbackStoreSet,
+    StoreScheduleLabel, TickSource,
 };
 
 /// A plugin adding rollback-friendly entity management to the app.
@@ -55,7 +56,9 @@ impl<Tick: TickSource> Plugin for EntityManagementPlugin<Tick> {
         .insert_resource(GetTick(|world| (*world.resource::<Tick>()).into()))
         .insert_resource(GetTickDeferred(|world| (*world.resource::<Tick>()).into()))
         .init_resource::<ToRemove>()
-        .add_systems(RollbackSchedule::BackToPresent, despawn_unspawned_entities);
+        .add_systems(RollbackSchedule::BackToPresent, despawn_unspawned_entities)
+        .add_systems(RollbackSchedule::Reset, reset_removals)
+        .add_observer(enable_matched_predictions);
     }
 }
 
@@ -116,36 +119,75 @@ struct GetTickDeferred(fn(&DeferredWorld) -> RepliconTick);
 
 impl<Reason: SpawnReason> Plugin for SpawnPlugin<Reason> {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SpawnedEntities<Reason>>().add_systems(
-            RollbackSchedule::BackToPresent,
-            (
-                |world: &World| -> RepliconTick { world.resource::<GetTick>().0(world) }
-                    .pipe(clean_spawned_entities_system::<Reason>),
-                reset_removals,
+        app.init_resource::<SpawnedEntities<Reason>>()
+            .add_systems(
+                RollbackSchedule::BackToPresent,
+                (
+                    |world: &World| -> RepliconTick { world.resource::<GetTick>().0(world) }
+                        .pipe(clean_spawned_entities_system::<Reason>),
+                    reset_removals,
+                )
+                    .chain(),
             )
-                .chain(),
-        );
+            .add_systems(RollbackSchedule::Reset, clear_spawned_entities::<Reason>);
     }
 }
 
-/// A marker for entities that should be despawned once they fall out of history
+/// A marker for entities that should be despawned once they fall out of history.
+/// Descendants are disabled along with the entity, and enabled again when it is removed.
 #[derive(Component, Clone, Copy)]
 #[component(on_insert=track_unused)]
 #[component(on_remove=untrack_unused)]
 #[require(Disabled, UnusedAt)]
 pub struct Despawned;
 
+/// A marker for entities that were despawned along with their parent
+#[derive(Component)]
+struct DespawnedWithParent;
+
 fn track_unused(mut world: DeferredWorld, ctx: HookContext) {
     let get_tick = world.resource::<GetTickDeferred>();
     let tick = get_tick.0(&world);
     world.commands().entity(ctx.entity).insert(UnusedAt(tick));
+
+    let children = children_with(&world, ctx.entity, |child| !child.contains::<Despawned>());
+    for child in children {
+        world
+            .commands()
+            .entity(child)
+            .try_insert((Despawned, DespawnedWithParent));
+    }
 }
 
 fn untrack_unused(mut world: DeferredWorld, ctx: HookContext) {
     world.commands().entity(ctx.entity).try_remove::<UnusedAt>();
+
+    let children = children_with(&world, ctx.entity, |child| {
+        child.contains::<DespawnedWithParent>()
+    });
+    for child in children {
+        world
+            .commands()
+            .entity(child)
+            .try_remove::<(Despawned, DespawnedWithParent)>();
+    }
     reenable(world, ctx)
 }
 
+fn children_with(
+    world: &DeferredWorld,
+    entity: Entity,
+    filter: impl Fn(EntityRef) -> bool,
+) -> Vec<Entity> {
+    let Some(children) = world.get::<Children>(entity) else {
+        return Vec::new();
+    };
+    children
+        .iter()
+        .filter(|&child| world.get_entity(child).is_ok_and(&filter))
+        .collect()
+}
+
 fn reenable(mut world: DeferredWorld, ctx: HookContext) {
     let despawned_id = world.component_id::<Despawned>().unwrap();
     let unspawned_id = world.component_id::<Unspawned>().unwrap();
@@ -186,13 +228,38 @@ fn despawn_unused_entities<Tick: TickSource>(
 
 fn despawn_unspawned_entities(
     mut commands: Commands,
-    query: Query<Entity, (With<Disabled>, With<Unspawned>)>,
+    query: Query<(Entity, Has<Signature>, Has<Replicated>), (With<Disabled>, With<Unspawned>)>,
 ) {
-    for entity in query.iter() {
+    for (entity, has_signature, is_replicated) in query.iter() {
+        if has_signature && !is_replicated {
+            // The server can still replicate the entity through its signature, keep it until it
+            // falls out of history so the server entity is matched to it instead of a new one
+            commands
+                .entity(entity)
+                .try_insert((Despawned, UnmatchedPrediction))
+                .try_remove::<Unspawned>();
+            continue;
+        }
         commands.entity(entity).try_despawn();
     }
 }
 
+/// A marker for predicted spawns kept for the server entity matching their signature
+#[derive(Component)]
+struct UnmatchedPrediction;
+
+fn enable_matched_predictions(
+    trigger: Trigger<OnAdd, Replicated>,
+    mut commands: Commands,
+    query: Query<(), (With<UnmatchedPrediction>, With<Disabled>)>,
+) {
+    if query.contains(trigger.target()) {
+        commands
+            .entity(trigger.target())
+            .try_remove::<(Despawned, UnmatchedPrediction)>();
+    }
+}
+
 impl<Reason: SpawnReason> SpawnedEntities<Reason> {
     fn get(&self, reason: &Reason) -> Option<Entity> {
         self.0.get(reason).map(|e| e.id)
@@ -243,6 +310,10 @@ fn clean_spawned_entities_system<Reason: SpawnReason>(
     });
 }
 
+fn clear_spawned_entities<Reason: SpawnReason>(mut entities: ResMut<SpawnedEntities<Reason>>) {
+    entities.0.clear();
+}
+
 fn reset_removals(mut removed: ResMut<ToRemove>) {
     removed.clear();
 }
@@ -263,13 +334,19 @@ impl EntityManagementCommands for Commands<'_, '_> {
         {
             if let Ok(mut entity_cmd) = self.get_entity(entity) {
                 entity_cmd.commands().queue(UpdateSpawnedEntity(reason));
-                entity_cmd.insert(bundle).remove::<(Despawned, Unspawned)>();
+                entity_cmd
+                    .insert(bundle)
+                    .remove::<(Despawned, Unspawned, UnmatchedPrediction)>();
                 return entity;
             }
             warn!("Failed to reuse {}, creating new entity", entity);
         }
 
         let new_entity = self.spawn((Reuse, bundle, Signature::from(&reason))).id();
+        if let Some(entity) = spawned.entities.get(&reason) {
+            // Stored values may still point to the entity that couldn't be reused
+            self.queue(RemapHistories::single(entity, new_entity));
+        }
         self.queue(InsertSpawnedEntity(reason, new_entity));
         new_entity
     }
@@ -321,16 +398,23 @@ impl EntityManagementWorld for World {
 
         let mut entities = self.resource_mut::<SpawnedEntities<Reason>>();
 
-        if let Some(entity) = entities.get_and_update(&reason, tick)
+        let previous = entities.get_and_update(&reason, tick);
+        if let Some(entity) = previous
             && !self.resource::<ToRemove>().contains(&entity)
             && self.entities().contains(entity)
         {
             let mut entity_mut = self.entity_mut(entity);
-            entity_mut.insert(bundle).remove::<(Despawned, Unspawned)>();
+            entity_mut
+                .insert(bundle)
+                .remove::<(Despawned, Unspawned, UnmatchedPrediction)>();
             return entity_mut;
         }
 
         let new_entity = self.spawn((Reuse, bundle, Signature::from(&reason))).id();
+        if let Some(entity) = previous {
+            // Stored values may still point to the entity that couldn't be reused
+            RemapHistories::single(entity, new_entity).apply(self);
+        }
         self.resource_mut::<SpawnedEntities<Reason>>()
             .insert(reason, tick, new_entity);
         return self.entity_mut(new_entity);
@@ -407,3 +491,178 @@ impl<Reason: SpawnReason> Command for UpdateSpawnedEntity<Reason> {
         entities.update(&self.0, tick);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::schedule::ScheduleLabel,
+        state::app::StatesPlugin,
+        time::{TimePlugin, TimeUpdateStrategy},
+    };
+    use bevy_replicon::{
+        client::{
+            confirm_history::EntityReplicated,
+            server_mutate_ticks::{MutateTickReceived, ServerMutateTicks},
+        },
+        prelude::RepliconSharedPlugin,
+    };
+    use bevy_rewind::{RollbackPlugin, RollbackTarget};
+
+    #[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+    struct Tick(u32);
+
+    impl From<RepliconTick> for Tick {
+        fn from(value: RepliconTick) -> Self {
+            Self(value.get())
+        }
+    }
+
+    impl From<Tick> for RepliconTick {
+        fn from(value: Tick) -> Self {
+            RepliconTick::new(value.0)
+        }
+    }
+
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    struct Spawn(u32);
+
+    impl SpawnReason for Spawn {
+        fn tick(&self) -> RepliconTick {
+            RepliconTick::new(self.0)
+        }
+    }
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct DespawnAt(Option<Tick>);
+
+    #[derive(Resource, Default, Deref, DerefMut)]
+    struct Parent(Option<Entity>);
+
+    #[derive(ScheduleLabel, Clone, PartialEq, Eq, Debug, Hash)]
+    struct Store;
+
+    fn init_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            StatesPlugin,
+            RepliconSharedPlugin::default(),
+            RollbackPlugin::<Tick> {
+                store_schedule: Store.intern(),
+                rollback_schedule: FixedUpdate.intern(),
+                phantom: PhantomData,
+            },
+            TimePlugin,
+        ))
+        .add_plugins((
+            EntityManagementPlugin::<Tick>::new(),
+            SpawnPlugin::<Spawn>::new(),
+        ))
+        .insert_state(ClientState::Connected)
+        .init_resource::<ServerMutateTicks>()
+        .add_event::<EntityReplicated>()
+        .add_event::<MutateTickReceived>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            16,
+        )))
+        .insert_resource(Tick(14));
+        app
+    }
+
+    #[test]
+    fn disables_and_reenables_children() {
+        let mut app = init_app();
+        app.init_resource::<DespawnAt>()
+            .init_resource::<Parent>()
+            .add_systems(
+                FixedUpdate,
+                |mut commands: Commands,
+                 spawned: Spawned<Spawn>,
+                 mut parent: ResMut<Parent>,
+                 despawn_at: Res<DespawnAt>,
+                 tick: Res<Tick>| {
+                    if *tick == Tick(14) {
+                        **parent =
+                            Some(commands.reuse_spawn(&spawned, Spawn(14), Name::new("parent")));
+                    }
+                    if **despawn_at == Some(*tick) {
+                        commands.disable_or_despawn(parent.unwrap());
+                    }
+                },
+            );
+        // The first update doesn't advance time
+        app.update();
+
+        app.update();
+        let parent = app.world().resource::<Parent>().unwrap();
+        let child = app.world_mut().spawn(ChildOf(parent)).id();
+
+        // Despawning the parent while resimulating disables its children along with it
+        app.insert_resource(Tick(15))
+            .insert_resource(DespawnAt(Some(Tick(14))));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert!(app.world().entity(parent).contains::<Disabled>());
+        assert!(app.world().entity(child).contains::<Disabled>());
+
+        // Rolling back to before the despawn reuses the parent, which enables its children again
+        app.insert_resource(Tick(16))
+            .insert_resource(DespawnAt(None));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(14));
+        app.update();
+        assert_eq!(Some(parent), **app.world().resource::<Parent>());
+        assert!(!app.world().entity(parent).contains::<Disabled>());
+        assert!(!app.world().entity(child).contains::<Disabled>());
+        assert!(!app.world().entity(child).contains::<Despawned>());
+    }
+
+    #[test]
+    fn keeps_unspawned_predictions_for_the_server() {
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct SpawnAt(Option<Tick>);
+
+        #[derive(Resource, Default, Deref, DerefMut)]
+        struct Shot(Option<Entity>);
+
+        let mut app = init_app();
+        app.init_resource::<SpawnAt>()
+            .init_resource::<Shot>()
+            .add_systems(
+                FixedUpdate,
+                |mut commands: Commands,
+                 spawned: Spawned<Spawn>,
+                 mut shot: ResMut<Shot>,
+                 spawn_at: Res<SpawnAt>,
+                 tick: Res<Tick>| {
+                    if **spawn_at == Some(*tick) {
+                        **shot =
+                            Some(commands.reuse_spawn(&spawned, Spawn(tick.0), Name::new("shot")));
+                    }
+                },
+            );
+        // The first update doesn't advance time
+        app.update();
+
+        app.insert_resource(Tick(15))
+            .insert_resource(SpawnAt(Some(Tick(15))));
+        app.update();
+        let entity = app.world().resource::<Shot>().unwrap();
+
+        // Rolling back to before the spawn without spawning again keeps the entity for the server
+        app.world_mut().entity_mut(entity).insert(Unspawned);
+        app.insert_resource(Tick(16)).insert_resource(SpawnAt(None));
+        **app.world_mut().resource_mut::<RollbackTarget>() = Some(RepliconTick::new(15));
+        app.update();
+        assert!(app.world().entity(entity).contains::<Disabled>());
+        assert!(app.world().entity(entity).contains::<Despawned>());
+
+        // Replicon matching the server entity to it enables it again
+        app.world_mut().entity_mut(entity).insert(Replicated);
+        app.world_mut().flush();
+        assert!(!app.world().entity(entity).contains::<Disabled>());
+        assert!(!app.world().entity(entity).contains::<Despawned>());
+    }
+}
@@ -79,7 +79,12 @@ pub trait SpawnReason:
 
 /// An extension trait for [`Commands`] for rollback-friendly entity management
 pub trait EntityManagementCommands {
-    /// Spawn an entity, reusing entities on client if matching
+    /// Spawn an entity, reusing entities on client if matching.
+    ///
+    /// New entities get a `Signature` from the reason, so the matching server entity is
+    /// replicated to them and their histories stay valid. Entities that are rolled back to before
+    /// they spawned and aren't spawned again stay disabled until they fall out of history, and
+    /// are enabled again if the matching server entity is received in the meantime.
     fn reuse_spawn<Reason: SpawnReason>(
         &mut self,
         spawned: &Spawned<Reason>,
@@ -95,19 +100,20 @@ pub trait EntityManagementCommands {
         entity: Entity,
     );
 
-    /// Disable an entity if doing rollback, otherwise despawn it
+    /// Disable an entity and its descendants if doing rollback, otherwise despawn them
     fn disable_or_despawn(&mut self, entity: Entity);
 }
 
 /// An extension trait for [`EntityWorldMut`] for rollback-friendly entity management
 pub trait EntityManagementEntityWorldMut {
-    /// Disable an entity if doing rollback, otherwise despawn it
+    /// Disable an entity and its descendants if doing rollback, otherwise despawn them
     fn disable_or_despawn(self);
 }
 
 /// An extension trait for [`World`] for rollback-friendly entity management
 pub trait EntityManagementWorld {
-    /// Spawn an entity, reusing entities on client if matching
+    /// Spawn an entity, reusing entities on client if matching, see
+    /// [`EntityManagementCommands::reuse_spawn`]
     fn reuse_spawn<'a, Reason: SpawnReason>(
         &'a mut self,
         spawn: Reason,
@@ -117,7 +123,7 @@ pub trait EntityManagementWorld {
     /// Register an entity, causing later spawns to reuse this entity
     fn register_reuse<Reason: SpawnReason>(&mut self, reason: Reason, entity: Entity);
 
-    /// Disable an entity if doing rollback, otherwise despawn it
+    /// Disable an entity and its descendants if doing rollback, otherwise despawn them
     fn disable_or_despawn(&mut self, entity: Entity);
 }
 
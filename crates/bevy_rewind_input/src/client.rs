@@ -3,7 +3,13 @@
 use crate::{HistoryFor, InputHistory, InputQueueSet, InputTrait, TickSource};
 
 use bevy::{ecs::schedule::InternedScheduleLabel, prelude::*};
-use bevy_replicon::{client::ClientSystems, prelude::ClientState};
+use bevy_replicon::{
+    client::ClientSystems,
+    prelude::{ClientState, ClientStats},
+    shared::replicon_tick::RepliconTick,
+};
+#[cfg(feature = "recording")]
+use bevy_rewind::{SessionRecorder, SessionReplay};
 
 pub(super) struct InputQueueClientPlugin<T: InputTrait, Tick: TickSource> {
     schedule: InternedScheduleLabel,
@@ -29,15 +35,28 @@ impl<T: InputTrait, Tick: TickSource> Plugin for InputQueueClientPlugin<T, Tick>
                 .after(ClientSystems::Receive)
                 .in_set(InputQueueSet::Network),
         )
+        .add_systems(
+            PreUpdate,
+            update_input_delay
+                .run_if(resource_exists::<InputDelay>)
+                .after(ClientSystems::Receive),
+        )
         .add_systems(
             self.schedule,
-            load_inputs::<T, Tick>
+            (
+                store_delayed_inputs::<T, Tick>.run_if(input_delayed),
+                load_inputs::<T, Tick>,
+            )
+                .chain()
                 .in_set(InputQueueSet::Load)
                 .run_if(in_state(ClientState::Connected)),
         )
         .add_systems(
             FixedPostUpdate,
-            store_inputs::<T, Tick>
+            (
+                store_inputs::<T, Tick>.run_if(not(input_delayed)),
+                reset_delayed_inputs::<T, Tick>.run_if(input_delayed),
+            )
                 .in_set(InputQueueSet::Clean)
                 .run_if(in_state(ClientState::Connected)),
         )
@@ -48,6 +67,18 @@ impl<T: InputTrait, Tick: TickSource> Plugin for InputQueueClientPlugin<T, Tick>
                 .before(ClientSystems::Send)
                 .in_set(InputQueueSet::Network),
         );
+
+        #[cfg(feature = "recording")]
+        app.add_systems(
+            self.schedule,
+            (
+                record_inputs::<T, Tick>.run_if(resource_exists::<SessionRecorder>),
+                replay_inputs::<T, Tick>.run_if(resource_exists::<SessionReplay>),
+            )
+                .after(load_inputs::<T, Tick>)
+                .in_set(InputQueueSet::Load)
+                .run_if(in_state(ClientState::Connected)),
+        );
     }
 }
 
@@ -55,6 +86,100 @@ impl<T: InputTrait, Tick: TickSource> Plugin for InputQueueClientPlugin<T, Tick>
 #[derive(Component)]
 pub struct InputAuthority;
 
+/// A delay applied to the inputs of entities with [`InputAuthority`]. Inputs sampled on a tick
+/// are stored for `tick + delay`, which gives them more time to reach the server and other
+/// clients. This trades input latency for fewer rollbacks.
+///
+/// The delay adapts to half of the measured round trip time, clamped between the minimum and
+/// maximum delay. It changes by at most one tick per frame, which drops a single input when
+/// the delay shrinks, or inserts a default input when it grows.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct InputDelay {
+    /// The minimum delay in ticks
+    pub min_ticks: u8,
+    /// The maximum delay in ticks
+    pub max_ticks: u8,
+    current: u8,
+}
+
+impl InputDelay {
+    /// Construct an `InputDelay` with a fixed number of ticks
+    pub fn fixed(ticks: u8) -> Self {
+        Self::adaptive(ticks, ticks)
+    }
+
+    /// Construct an `InputDelay` adapting to the round trip time, within the range of ticks
+    pub fn adaptive(min_ticks: u8, max_ticks: u8) -> Self {
+        Self {
+            min_ticks,
+            max_ticks: max_ticks.max(min_ticks),
+            current: min_ticks,
+        }
+    }
+
+    /// The delay currently applied to inputs, in ticks
+    pub fn current(&self) -> u8 {
+        self.current
+    }
+}
+
+fn input_delayed(delay: Option<Res<InputDelay>>) -> bool {
+    delay.is_some_and(|delay| delay.current() > 0)
+}
+
+fn update_input_delay(
+    mut delay: ResMut<InputDelay>,
+    stats: Option<Res<ClientStats>>,
+    time: Res<Time<Fixed>>,
+) {
+    let one_way = stats.map(|stats| stats.rtt / 2.).unwrap_or_default();
+    let ticks = (one_way / time.timestep().as_secs_f64()).ceil();
+    let target = (ticks as u8).clamp(delay.min_ticks, delay.max_ticks);
+
+    let current = delay.current;
+    let next = match target.cmp(&current) {
+        std::cmp::Ordering::Greater => current + 1,
+        std::cmp::Ordering::Less => current - 1,
+        std::cmp::Ordering::Equal => return,
+    };
+    delay.current = next;
+}
+
+fn store_delayed_inputs<T: InputTrait, Tick: TickSource>(
+    mut query: Query<(&mut InputHistory<T>, &mut T), With<InputAuthority>>,
+    tick: Res<Tick>,
+    delay: Res<InputDelay>,
+) {
+    let tick: RepliconTick = (*tick).into();
+    let target = tick + delay.current() as u32;
+    for (mut hist, mut input) in query.iter_mut() {
+        // Inputs were already stored for this tick, for example while resimulating or when the
+        // delay shrank. Histories ahead of the tick are reset by `reset_delayed_inputs`
+        if hist.updated_at() >= target {
+            continue;
+        }
+
+        let taken = std::mem::take(&mut *input);
+        hist.write(target, taken);
+    }
+}
+
+/// Reset histories that are ahead of the delayed tick, like [`store_inputs`] does for the current
+/// tick. This runs outside of the simulation schedule, so resimulated ticks don't reset them.
+fn reset_delayed_inputs<T: InputTrait, Tick: TickSource>(
+    mut query: Query<&mut InputHistory<T>, With<InputAuthority>>,
+    tick: Res<Tick>,
+    delay: Res<InputDelay>,
+) {
+    let tick: RepliconTick = (*tick).into();
+    let target = tick + delay.current() as u32;
+    for mut hist in query.iter_mut() {
+        if hist.updated_at() > target {
+            hist.reset();
+        }
+    }
+}
+
 fn store_inputs<T: InputTrait, Tick: TickSource>(
     mut query: Query<(&mut InputHistory<T>, &mut T), With<InputAuthority>>,
     tick: Res<Tick>,
@@ -88,6 +213,36 @@ fn load_inputs<T: InputTrait, Tick: TickSource>(
     }
 }
 
+/// Record the loaded inputs, which covers both stored and received inputs
+#[cfg(feature = "recording")]
+fn record_inputs<T: InputTrait, Tick: TickSource>(
+    mut recorder: ResMut<SessionRecorder>,
+    query: Query<(Entity, &T), With<InputHistory<T>>>,
+    tick: Res<Tick>,
+) {
+    for (entity, input) in query.iter() {
+        recorder.record_input(entity, (*tick).into(), input);
+    }
+}
+
+/// Replace the loaded inputs with the recorded ones
+#[cfg(feature = "recording")]
+fn replay_inputs<T: InputTrait, Tick: TickSource>(
+    mut commands: Commands,
+    replay: Res<SessionReplay>,
+    mut query: Query<&mut T>,
+    tick: Res<Tick>,
+) {
+    for (entity, recorded) in replay.inputs::<T>((*tick).into()) {
+        match query.get_mut(entity) {
+            Ok(mut input) => *input = recorded,
+            Err(_) => {
+                commands.entity(entity).insert(recorded);
+            }
+        }
+    }
+}
+
 fn send_input_events<T: InputTrait>(
     hist: Query<&InputHistory<T>, With<InputAuthority>>,
     mut events: EventWriter<InputHistory<T>>,
@@ -163,6 +318,129 @@ mod tests {
         assert_eq!(hist(0, []), *e.get::<InputHistory<A>>().unwrap());
     }
 
+    #[test]
+    fn stores_delayed_inputs() {
+        let mut app = App::new();
+        app.add_systems(
+            Update,
+            (store_delayed_inputs::<A, Tick>, load_inputs::<A, Tick>).chain(),
+        )
+        .insert_resource(InputDelay::fixed(2))
+        .insert_resource(Tick(5));
+        let e = app
+            .world_mut()
+            .spawn((A(4), InputHistory::<A>::default(), InputAuthority))
+            .id();
+
+        for (tick, input) in [(5, 4), (6, 5), (7, 6)] {
+            app.insert_resource(Tick(tick));
+            app.world_mut().entity_mut(e).insert(A(input));
+            app.update();
+        }
+
+        // Inputs are stored two ticks ahead, and loaded once their tick is reached
+        let entity = app.world().entity(e);
+        assert_eq!(
+            hist(7, [A(4), A(5), A(6)]),
+            *entity.get::<InputHistory<A>>().unwrap()
+        );
+        assert_eq!(Some(&A(4)), entity.get::<A>());
+
+        // Resimulated ticks don't overwrite the stored inputs
+        app.insert_resource(Tick(6));
+        app.update();
+        let entity = app.world().entity(e);
+        assert_eq!(
+            9,
+            entity.get::<InputHistory<A>>().unwrap().updated_at().get()
+        );
+        assert_eq!(Some(&A(4)), entity.get::<A>());
+    }
+
+    #[test]
+    fn changes_input_delay() {
+        let mut app = App::new();
+        app.add_systems(
+            Update,
+            (store_delayed_inputs::<A, Tick>, load_inputs::<A, Tick>).chain(),
+        )
+        .insert_resource(InputDelay::fixed(1));
+        let e = app
+            .world_mut()
+            .spawn((InputHistory::<A>::default(), InputAuthority))
+            .id();
+
+        // The delay grows from one to two ticks on tick 6, shrinks back to one on tick 8
+        for (tick, delay, input) in [(5, 1, 1), (6, 2, 2), (7, 2, 3), (8, 1, 4), (9, 1, 5)] {
+            app.insert_resource(Tick(tick))
+                .insert_resource(InputDelay::fixed(delay));
+            app.world_mut().entity_mut(e).insert(A(input));
+            app.update();
+        }
+
+        // A default input fills the tick skipped when growing,
+        // the input sampled on tick 8 is dropped when shrinking
+        assert_eq!(
+            hist(6, [A(1), A::default(), A(2), A(3), A(5)]),
+            *app.world().entity(e).get::<InputHistory<A>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn resets_delayed_inputs() {
+        let mut app = App::new();
+        app.add_systems(Update, reset_delayed_inputs::<A, Tick>)
+            .insert_resource(InputDelay::fixed(2))
+            .insert_resource(Tick(7));
+        let e1 = app
+            .world_mut()
+            .spawn((hist(7, [A(1), A(2), A(3)]), InputAuthority))
+            .id();
+        let e2 = app
+            .world_mut()
+            .spawn((hist(8, [A(1), A(2), A(3)]), InputAuthority))
+            .id();
+
+        app.update();
+
+        // The history stored up to the delayed tick is kept
+        assert_eq!(
+            hist(7, [A(1), A(2), A(3)]),
+            *app.world().entity(e1).get::<InputHistory<A>>().unwrap()
+        );
+        // While the one ahead of it is reset, for example after the tick jumped back
+        assert!(
+            app.world()
+                .entity(e2)
+                .get::<InputHistory<A>>()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn adapts_input_delay() {
+        let mut app = App::new();
+        app.add_systems(Update, update_input_delay)
+            .insert_resource(InputDelay::adaptive(1, 3))
+            .insert_resource(Time::<Fixed>::from_hz(50.))
+            .insert_resource(ClientStats {
+                rtt: 0.2,
+                ..Default::default()
+            });
+
+        // A one way latency of 100ms is 5 ticks, the delay grows one tick per frame
+        app.update();
+        assert_eq!(2, app.world().resource::<InputDelay>().current());
+        app.update();
+        app.update();
+        assert_eq!(3, app.world().resource::<InputDelay>().current());
+
+        app.world_mut().resource_mut::<ClientStats>().rtt = 0.;
+        app.update();
+        assert_eq!(2, app.world().resource::<InputDelay>().current());
+    }
+
     #[test]
     fn sends_inputs_with_authority() {
         let mut app = App::new();
@@ -216,6 +494,57 @@ mod tests {
         assert_eq!(A(1), *e.get::<A>().unwrap());
     }
 
+    #[cfg(feature = "recording")]
+    #[test]
+    fn records_and_replays_inputs() {
+        use bevy::time::TimePlugin;
+        use bevy_replicon::client::{
+            confirm_history::{ConfirmHistory, EntityReplicated},
+            server_mutate_ticks::{MutateTickReceived, ServerMutateTicks},
+        };
+        use bevy_rewind::{SessionRecorderPlugin, SessionReplayPlugin};
+
+        fn init_app() -> App {
+            let mut app = App::new();
+            app.add_plugins(TimePlugin)
+                .insert_resource(Tick(5))
+                .init_resource::<ServerMutateTicks>()
+                .add_event::<EntityReplicated>()
+                .add_event::<MutateTickReceived>()
+                .add_event::<AppExit>();
+            app
+        }
+
+        let mut app = init_app();
+        app.add_plugins(SessionRecorderPlugin::<Tick>::default())
+            .add_systems(Update, record_inputs::<A, Tick>);
+        let entity = app
+            .world_mut()
+            .spawn((
+                A(3),
+                InputHistory::<A>::default(),
+                ConfirmHistory::new(Tick(0).into()),
+            ))
+            .id();
+        app.update();
+
+        let recording = app
+            .world_mut()
+            .resource_mut::<SessionRecorder>()
+            .take_recording();
+        assert_eq!(1, recording.frames[0].inputs.len());
+
+        // The replaying entity gets the recorded input for the tick
+        let mut app = init_app();
+        app.add_plugins(SessionReplayPlugin::<Tick>::new(recording))
+            .add_systems(Update, replay_inputs::<A, Tick>);
+        app.update();
+
+        let replayed = app.world().resource::<SessionReplay>().entity(entity);
+        let replayed = replayed.unwrap();
+        assert_eq!(Some(&A(3)), app.world().get::<A>(replayed));
+    }
+
     #[test]
     fn receive_input_writes_history() {
         let mut app = App::new();
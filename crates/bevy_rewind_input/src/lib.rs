@@ -13,7 +13,7 @@ pub use history::InputHistory;
 #[cfg(feature = "client")]
 mod client;
 #[cfg(feature = "client")]
-pub use client::InputAuthority;
+pub use client::{InputAuthority, InputDelay};
 #[cfg(feature = "server")]
 mod server;
 #[cfg(feature = "server")]
@@ -0,0 +1,105 @@
+//! Derive macros for `bevy_rewind`.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{DeriveInput, Error, Path, parse_macro_input};
+
+/// The rollback policy of a component
+enum Policy {
+    Authoritative,
+    Predicted,
+}
+
+/// Derive `RollbackComponent`, registering the component according to its `#[rollback]` attribute.
+///
+/// Derived components are registered with `register_rollback_component`, or through
+/// `RollbackRegistrationPlugin` when they also reflect `RollbackComponent`.
+///
+/// Supported attributes:
+/// - `#[rollback(authoritative)]` or `#[rollback(predicted)]` to select the policy
+/// - `load = path::to::fn` to register the component with a custom load function
+/// - `serialized` to store the history as serialized bytes instead of cloned values
+#[proc_macro_derive(Rollback, attributes(rollback))]
+pub fn derive_rollback(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_rollback_internal(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn derive_rollback_internal(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let mut policy = None;
+    let mut load = None::<Path>;
+    let mut serialized = false;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("rollback")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("authoritative") || meta.path.is_ident("predicted") {
+                if policy.is_some() {
+                    return Err(meta.error("the rollback policy is already specified"));
+                }
+                policy = Some(if meta.path.is_ident("authoritative") {
+                    Policy::Authoritative
+                } else {
+                    Policy::Predicted
+                });
+                return Ok(());
+            }
+            if meta.path.is_ident("load") {
+                load = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+            if meta.path.is_ident("serialized") {
+                serialized = true;
+                return Ok(());
+            }
+            Err(meta.error("expected `authoritative`, `predicted`, `load` or `serialized`"))
+        })?;
+    }
+
+    let Some(policy) = policy else {
+        return Err(Error::new(
+            Span::call_site(),
+            "expected `#[rollback(authoritative)]` or `#[rollback(predicted)]`",
+        ));
+    };
+
+    let register = match (policy, load, serialized) {
+        (_, Some(load), true) => {
+            return Err(Error::new_spanned(
+                load,
+                "`load` is not supported for serialized components",
+            ));
+        }
+        (Policy::Authoritative, None, false) => {
+            quote! { register_authoritative_component::<Self>(app) }
+        }
+        (Policy::Predicted, None, false) => {
+            quote! { register_predicted_component::<Self>(app) }
+        }
+        (Policy::Authoritative, Some(load), false) => {
+            quote! { register_authoritative_component_with_load::<Self>(app, #load) }
+        }
+        (Policy::Predicted, Some(load), false) => {
+            quote! { register_predicted_component_with_load::<Self>(app, #load) }
+        }
+        (Policy::Authoritative, None, true) => {
+            quote! { register_serialized_authoritative_component::<Self>(app) }
+        }
+        (Policy::Predicted, None, true) => {
+            quote! { register_serialized_predicted_component::<Self>(app) }
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::bevy_rewind::RollbackComponent for #name #ty_generics #where_clause {
+            fn register_rollback(app: &mut ::bevy_rewind::__macro_exports::App) {
+                ::bevy_rewind::RollbackApp::#register;
+            }
+        }
+    })
+}